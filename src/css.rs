@@ -0,0 +1,97 @@
+use super::user_config::class_scheme::ClassScheme;
+use super::user_config::theme::{ColorScheme, Theme};
+use super::user_config::treesitter_util::HIGHLIGHT_NAMES;
+use std::collections::{BTreeMap, BTreeSet};
+
+type Declarations = Vec<(&'static str, String)>;
+
+/// Formats a single css rule, indenting it by `indent`.
+fn rule(selector: &str, declarations: &[(impl AsRef<str>, String)], indent: &str) -> String {
+    let mut rule = format!("{indent}{selector} {{\n");
+    for (property, value) in declarations {
+        rule += format!("{indent}  {}: {value};\n", property.as_ref()).as_str();
+    }
+    rule += format!("{indent}}}\n").as_str();
+    return rule;
+}
+
+/// Returns the declarations defining `palette` as custom properties.
+fn custom_properties(palette: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    return palette
+        .iter()
+        .map(|(name, color)| (format!("--{name}"), color.clone()))
+        .collect();
+}
+
+/// Returns the declarations for every entry of [`HIGHLIGHT_NAMES`] which `theme` has a style for.
+/// Palette colors are referenced through custom properties, so that color scheme variants
+/// only have to redefine the palette.
+fn highlight_declarations(theme: &Theme) -> Vec<(&'static str, Declarations)> {
+    let mut rules = Vec::new();
+    for name in HIGHLIGHT_NAMES {
+        if let Some(style) = theme.style_for(name) {
+            let declarations = style.declarations(|color| {
+                if theme.palette.contains_key(color) {
+                    return format!("var(--{color})");
+                }
+                return color.into();
+            });
+            rules.push((name, declarations));
+        }
+    }
+    return rules;
+}
+
+/// Generates a stylesheet for every entry of [`HIGHLIGHT_NAMES`] from `theme`, with selectors for
+/// the classes written by `class_scheme`. Where several names share the same classes, as in the
+/// presets for other highlighters, only the first (most general) name gets a rule.
+///
+/// Names without a style of their own use the style of their dotted parent, and names without
+/// either get no rule, see [`Theme::missing_styles`]. If the theme has
+/// `light` or `dark` variants, their palettes and any rules that differ from the base theme are
+/// written under the matching `prefers-color-scheme` media query.
+pub fn stylesheet(theme: &Theme, class_scheme: &ClassScheme, class_prefix: &str) -> String {
+    let mut css = String::new();
+    if !theme.palette.is_empty() {
+        css += rule(":root", &custom_properties(&theme.palette), "").as_str();
+    }
+
+    let base_rules = highlight_declarations(theme);
+    let mut selectors: BTreeSet<String> = BTreeSet::new();
+    for (name, declarations) in base_rules.iter() {
+        let Some(selector) = class_scheme.selector(class_prefix, name) else {
+            continue;
+        };
+        if selectors.insert(selector.clone()) {
+            css += rule(&selector, declarations, "").as_str();
+        }
+    }
+
+    for scheme in [ColorScheme::Light, ColorScheme::Dark] {
+        let Some(variant) = theme.variant(scheme) else {
+            continue;
+        };
+        let mut body = String::new();
+        if !variant.palette.is_empty() {
+            body += rule(":root", &custom_properties(&variant.palette), "  ").as_str();
+        }
+        let mut selectors: BTreeSet<String> = BTreeSet::new();
+        for scheme_rule in highlight_declarations(&theme.with_scheme(scheme)) {
+            let Some(selector) = class_scheme.selector(class_prefix, scheme_rule.0) else {
+                continue;
+            };
+            if selectors.insert(selector.clone()) && !base_rules.contains(&scheme_rule) {
+                body += rule(&selector, &scheme_rule.1, "  ").as_str();
+            }
+        }
+        if !body.is_empty() {
+            css += format!(
+                "@media (prefers-color-scheme: {}) {{\n{}}}\n",
+                scheme.name(),
+                body
+            )
+            .as_str();
+        }
+    }
+    return css;
+}
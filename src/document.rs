@@ -0,0 +1,659 @@
+use super::info_string::InfoString;
+use super::renderer::html::{attribute_escape, html_escape};
+use std::collections::BTreeMap;
+use tree_sitter::{Node, Parser, Range, Tree};
+
+/// The template used for standalone documents if none is configured.
+pub const DEFAULT_TEMPLATE: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{{title}}</title>
+{{stylesheet}}
+</head>
+<body>
+{{content}}
+</body>
+</html>
+";
+
+/// A markdown file rendered to html, ready to be placed into a template.
+#[derive(Debug)]
+pub struct HtmlDocument {
+    /// The `title` from the front matter, or the text of the first level 1 heading.
+    pub title: String,
+    pub body: String,
+}
+
+impl HtmlDocument {
+    /// Fills the `{{title}}`, `{{content}}` and `{{stylesheet}}` slots of `template`.
+    /// `stylesheet` is placed into a `<style>` element, if there is one.
+    pub fn fill_template(&self, template: &str, stylesheet: Option<&str>) -> String {
+        let stylesheet = match stylesheet {
+            Some(css) => format!("<style>\n{css}</style>"),
+            None => "".into(),
+        };
+        return template
+            .replace("{{title}}", &html_escape(&self.title))
+            .replace("{{stylesheet}}", &stylesheet)
+            .replace("{{content}}", &self.body);
+    }
+}
+
+/// Normalizes a link label for matching references, as labels are case-insensitive, and
+/// ignore differences in whitespace.
+fn normalize_label(label: &str) -> String {
+    let label = label.trim_start_matches('[').trim_end_matches(']');
+    return label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+}
+
+/// Strips the angle brackets from `<destination>`, and the quotes or parentheses from a title.
+fn strip_delimiters(text: &str) -> &str {
+    for (open, close) in [('<', '>'), ('"', '"'), ('\'', '\''), ('(', ')')] {
+        if let Some(inner) = text.strip_prefix(open).and_then(|t| t.strip_suffix(close)) {
+            return inner;
+        }
+    }
+    return text;
+}
+
+/// Returns the ranges of the inline content of `node`, i.e. an `inline` or `pipe_table_cell`, for
+/// parsing it with `tree_sitter_md::INLINE_LANGUAGE`. Named children of inline nodes (block
+/// continuations, e.g. `> ` inside of block quotes) are not part of the content.
+pub fn inline_ranges(node: Node) -> Vec<Range> {
+    let mut ranges = Vec::new();
+    let mut range = node.range();
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        ranges.push(Range {
+            start_byte: range.start_byte,
+            start_point: range.start_point,
+            end_byte: child.start_byte(),
+            end_point: child.start_position(),
+        });
+        range.start_byte = child.end_byte();
+        range.start_point = child.end_position();
+    }
+    ranges.push(range);
+    ranges.retain(|r| r.start_byte < r.end_byte);
+    return ranges;
+}
+
+/// Returns the text of `source` between `start` and `end`, without text outside of `ranges`.
+fn inline_text(source: &str, ranges: &[Range], start: usize, end: usize) -> String {
+    let mut text = String::new();
+    for range in ranges.iter() {
+        let (s, e) = (range.start_byte.max(start), range.end_byte.min(end));
+        if s < e {
+            text += &source[s..e];
+        }
+    }
+    return text;
+}
+
+/// Returns the code of the inline `code_span` node, parsed from `ranges` of `source`.
+/// Line breaks become spaces, and one space is stripped from both sides, so that code can start
+/// with a backtick.
+pub fn code_span_contents(source: &str, ranges: &[Range], node: Node) -> String {
+    let mut cursor = node.walk();
+    let delimiters: Vec<Node> = node
+        .children(&mut cursor)
+        .filter(|c| c.kind() == "code_span_delimiter")
+        .collect();
+    let code = match (delimiters.first(), delimiters.last()) {
+        (Some(open), Some(close)) if delimiters.len() > 1 => {
+            inline_text(source, ranges, open.end_byte(), close.start_byte())
+        }
+        _ => inline_text(source, ranges, node.start_byte(), node.end_byte()),
+    };
+    let code = code.replace('\n', " ");
+    match code.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+        Some(inner) if !inner.trim().is_empty() => return inner.into(),
+        _ => return code,
+    }
+}
+
+/// Writes the non-code parts of a markdown file as html, from the block tree of
+/// `tree_sitter_md::LANGUAGE`. The contents of paragraphs, headings and table cells are parsed
+/// with `tree_sitter_md::INLINE_LANGUAGE` as they are encountered.
+///
+/// Fenced code blocks and inline code spans with an entry in `code_blocks` are replaced by that
+/// entry, every other block is written as plain `<pre><code>`. Entries are keyed by their start
+/// byte, and hold the end of the replaced text, which may extend past the code span, e.g. for
+/// `` `x`{.rust} ``.
+pub struct HtmlWriter<'a> {
+    source: &'a str,
+    code_blocks: &'a BTreeMap<usize, (usize, String)>,
+    /// destinations and titles of link reference definitions, keyed by their normalized label.
+    references: BTreeMap<String, (String, Option<String>)>,
+    inline_parser: Parser,
+    /// the ranges of the inline content which is currently being written. Text outside of
+    /// these, e.g. `> ` inside of block quotes, is not part of the content.
+    inline_ranges: Vec<Range>,
+    title: Option<String>,
+    out: String,
+}
+
+impl<'a> HtmlWriter<'a> {
+    pub fn new(
+        source: &'a str,
+        code_blocks: &'a BTreeMap<usize, (usize, String)>,
+    ) -> HtmlWriter<'a> {
+        let mut inline_parser = Parser::new();
+        inline_parser
+            .set_language(&tree_sitter_md::INLINE_LANGUAGE.into())
+            .unwrap();
+        return HtmlWriter {
+            source,
+            code_blocks,
+            references: BTreeMap::new(),
+            inline_parser,
+            inline_ranges: Vec::new(),
+            title: None,
+            out: String::new(),
+        };
+    }
+
+    /// Writes the document parsed into `tree`.
+    pub fn write(mut self, tree: &Tree) -> HtmlDocument {
+        // references may be used before they are defined, so they are collected up front.
+        self.collect_references(tree.root_node());
+        self.block(tree.root_node(), false);
+        return HtmlDocument {
+            title: self.title.unwrap_or_default(),
+            body: self.out,
+        };
+    }
+
+    fn text(&self, node: Node) -> &'a str {
+        return &self.source[node.byte_range()];
+    }
+
+    /// Returns the text of the block `node`, without the prefixes of the containers it is nested
+    /// in, e.g. `> ` inside of block quotes.
+    fn block_text(&self, node: Node) -> String {
+        let mut text = String::new();
+        let mut position = node.start_byte();
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if child.kind() == "block_continuation" {
+                text += &self.source[position..child.start_byte()];
+                position = child.end_byte();
+            }
+        }
+        text += &self.source[position..node.end_byte()];
+        return text;
+    }
+
+    fn collect_references(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if child.kind() != "link_reference_definition" {
+                self.collect_references(child);
+                continue;
+            }
+            let source = self.source;
+            let field = |kind: &str| {
+                let mut cursor = child.walk();
+                return child
+                    .named_children(&mut cursor)
+                    .find(|c| c.kind() == kind)
+                    .map(|c| strip_delimiters(&source[c.byte_range()]).to_string());
+            };
+            if let (Some(label), Some(destination)) =
+                (field("link_label"), field("link_destination"))
+            {
+                let _ = self
+                    .references
+                    .entry(normalize_label(&label))
+                    .or_insert((destination, field("link_title")));
+            }
+        }
+    }
+
+    /// Writes the children of `node`. In tight lists, paragraphs are written without `<p>`.
+    fn children(&mut self, node: Node, tight: bool) {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            self.block(child, tight);
+        }
+    }
+
+    fn block(&mut self, node: Node, tight: bool) {
+        match node.kind() {
+            "document" | "section" => self.children(node, false),
+            "atx_heading" | "setext_heading" => self.heading(node),
+            "paragraph" => {
+                if !tight {
+                    self.out += "<p>";
+                }
+                self.inline_children(node);
+                if !tight {
+                    self.out += "</p>";
+                }
+                self.out += "\n";
+            }
+            "block_quote" => {
+                self.out += "<blockquote>\n";
+                self.children(node, false);
+                self.out += "</blockquote>\n";
+            }
+            "list" => self.list(node),
+            "thematic_break" => self.out += "<hr>\n",
+            "indented_code_block" => {
+                let code: Vec<&str> = self
+                    .text(node)
+                    .trim_end()
+                    .lines()
+                    .map(|line| line.strip_prefix("    ").unwrap_or(line.trim_start()))
+                    .collect();
+                self.out += format!(
+                    "<pre><code>{}\n</code></pre>\n",
+                    html_escape(&code.join("\n"))
+                )
+                .as_str();
+            }
+            "fenced_code_block" => self.fenced_code_block(node),
+            "html_block" => self.out += self.block_text(node).as_str(),
+            "minus_metadata" => {
+                let title = self
+                    .text(node)
+                    .lines()
+                    .find_map(|l| l.strip_prefix("title:"));
+                if let Some(title) = title {
+                    self.title = Some(strip_delimiters(title.trim()).to_string());
+                }
+            }
+            "pipe_table" => self.table(node),
+            // front matter, reference definitions, markers and continuations are not written.
+            _ => {}
+        }
+    }
+
+    /// Writes every `inline` child of `node`.
+    fn inline_children(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            if child.kind() == "inline" {
+                self.inline(child);
+            }
+        }
+    }
+
+    fn heading(&mut self, node: Node) {
+        let mut level = 1;
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            match child.kind() {
+                "setext_h2_underline" => level = 2,
+                kind if kind.starts_with("atx_h") && kind.ends_with("_marker") => {
+                    level = kind[5..6].parse().unwrap_or(1);
+                }
+                _ => {}
+            }
+        }
+        let start = self.out.len();
+        self.out += format!("<h{level}>").as_str();
+        if node.kind() == "setext_heading" {
+            let mut cursor = node.walk();
+            let paragraphs: Vec<Node> = node
+                .named_children(&mut cursor)
+                .filter(|c| c.kind() == "paragraph")
+                .collect();
+            for paragraph in paragraphs {
+                self.inline_children(paragraph);
+            }
+        } else {
+            self.inline_children(node);
+        }
+        if level == 1 && self.title.is_none() {
+            // the title is plain text, so any markup of the heading is dropped.
+            let mut title = String::new();
+            let mut in_tag = false;
+            for c in self.out[start + 4..].chars() {
+                match c {
+                    '<' => in_tag = true,
+                    '>' => in_tag = false,
+                    _ if !in_tag => title.push(c),
+                    _ => {}
+                }
+            }
+            self.title = Some(
+                title
+                    .replace("&lt;", "<")
+                    .replace("&gt;", ">")
+                    .replace("&quot;", "\"")
+                    .replace("&amp;", "&"),
+            );
+        }
+        self.out += format!("</h{level}>\n").as_str();
+    }
+
+    fn list(&mut self, node: Node) {
+        let mut cursor = node.walk();
+        let items: Vec<Node> = node.named_children(&mut cursor).collect();
+        let marker = items.first().and_then(|item| item.named_child(0));
+        let ordered = marker.is_some_and(|m| {
+            return m.kind() == "list_marker_dot" || m.kind() == "list_marker_parenthesis";
+        });
+        // a list is loose if any of its items are separated by a blank line.
+        let tight = !items.iter().rev().skip(1).any(|item| {
+            let text = self.text(*item);
+            return text.ends_with("\n\n") || text.ends_with("\n\r\n");
+        });
+        if ordered {
+            let marker_text = self.text(marker.unwrap()).trim();
+            let start: usize = marker_text[..marker_text.len() - 1].parse().unwrap_or(1);
+            if start == 1 {
+                self.out += "<ol>\n";
+            } else {
+                self.out += format!("<ol start=\"{start}\">\n").as_str();
+            }
+        } else {
+            self.out += "<ul>\n";
+        }
+        for item in items {
+            self.out += "<li>";
+            let mut cursor = item.walk();
+            for child in item.named_children(&mut cursor) {
+                match child.kind() {
+                    "task_list_marker_checked" => {
+                        self.out += "<input type=\"checkbox\" disabled checked> ";
+                    }
+                    "task_list_marker_unchecked" => {
+                        self.out += "<input type=\"checkbox\" disabled> ";
+                    }
+                    "paragraph" if tight => {
+                        self.inline_children(child);
+                    }
+                    kind if kind.starts_with("list_marker") || kind == "block_continuation" => {}
+                    _ => {
+                        if !self.out.ends_with('\n') {
+                            self.out += "\n";
+                        }
+                        self.block(child, tight);
+                    }
+                }
+            }
+            self.out += "</li>\n";
+        }
+        self.out += if ordered { "</ol>\n" } else { "</ul>\n" };
+    }
+
+    fn fenced_code_block(&mut self, node: Node) {
+        if let Some((_, rendered)) = self.code_blocks.get(&node.start_byte()) {
+            self.out += rendered.as_str();
+            return;
+        }
+        let mut language = None;
+        let mut code = String::new();
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            match child.kind() {
+                "info_string" => language = InfoString::parse(self.text(child)).language,
+                "code_fence_content" => code = self.block_text(child),
+                _ => {}
+            }
+        }
+        match language {
+            Some(language) => {
+                self.out += format!(
+                    "<pre><code class=\"language-{}\">",
+                    attribute_escape(&language)
+                )
+                .as_str();
+            }
+            None => self.out += "<pre><code>",
+        }
+        self.out += html_escape(&code).as_str();
+        self.out += "</code></pre>\n";
+    }
+
+    fn table(&mut self, node: Node) {
+        let mut alignments: Vec<Option<&str>> = Vec::new();
+        let mut cursor = node.walk();
+        let rows: Vec<Node> = node.named_children(&mut cursor).collect();
+        for row in rows
+            .iter()
+            .filter(|r| r.kind() == "pipe_table_delimiter_row")
+        {
+            let mut cursor = row.walk();
+            for cell in row.named_children(&mut cursor) {
+                let text = self.text(cell).trim();
+                alignments.push(match (text.starts_with(':'), text.ends_with(':')) {
+                    (true, true) => Some("center"),
+                    (true, false) => Some("left"),
+                    (false, true) => Some("right"),
+                    (false, false) => None,
+                });
+            }
+        }
+
+        self.out += "<table>\n";
+        for row in rows {
+            let cell_tag = match row.kind() {
+                "pipe_table_header" => {
+                    self.out += "<thead>\n";
+                    "th"
+                }
+                "pipe_table_row" => "td",
+                _ => continue,
+            };
+            self.out += "<tr>\n";
+            let mut cursor = row.walk();
+            let cells: Vec<Node> = row
+                .named_children(&mut cursor)
+                .filter(|c| c.kind() == "pipe_table_cell")
+                .collect();
+            for (i, cell) in cells.into_iter().enumerate() {
+                match alignments.get(i).copied().flatten() {
+                    Some(align) => {
+                        self.out += format!("<{cell_tag} style=\"text-align: {align}\">").as_str();
+                    }
+                    None => self.out += format!("<{cell_tag}>").as_str(),
+                }
+                self.inline(cell);
+                self.out += format!("</{cell_tag}>\n").as_str();
+            }
+            self.out += "</tr>\n";
+            if row.kind() == "pipe_table_header" {
+                self.out += "</thead>\n<tbody>\n";
+            }
+        }
+        self.out += "</tbody>\n</table>\n";
+    }
+
+    /// Parses and writes the inline content of `node`, i.e. an `inline` or `pipe_table_cell`.
+    fn inline(&mut self, node: Node) {
+        let ranges = inline_ranges(node);
+        if ranges.is_empty() {
+            return;
+        }
+
+        self.inline_parser.set_included_ranges(&ranges).unwrap();
+        let tree = self.inline_parser.parse(self.source, None).unwrap();
+        self.inline_ranges = ranges;
+        let root = tree.root_node();
+        // the root spans from the first to the last token, so surrounding whitespace is dropped.
+        self.inline_content(root, root.start_byte(), root.end_byte());
+        self.out.truncate(self.out.trim_end().len());
+    }
+
+    /// Returns the content between `start` and `end`, without text outside of the
+    /// current inline ranges.
+    fn inline_text(&self, start: usize, end: usize) -> String {
+        return inline_text(self.source, &self.inline_ranges, start, end);
+    }
+
+    /// Writes the children of the inline `node` between `start` and `end`, with the text
+    /// between them escaped.
+    fn inline_content(&mut self, node: Node, start: usize, end: usize) {
+        let mut position = start;
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if child.end_byte() <= start
+                || child.start_byte() >= end
+                || child.start_byte() < position
+            {
+                continue;
+            }
+            self.out += html_escape(&self.inline_text(position, child.start_byte())).as_str();
+            if let Some((replaced_end, rendered)) = self.code_blocks.get(&child.start_byte()) {
+                // highlighted code spans, which may include an attribute after the span.
+                self.out += rendered.as_str();
+                position = *replaced_end;
+                continue;
+            }
+            self.inline_node(child);
+            position = child.end_byte();
+        }
+        self.out += html_escape(&self.inline_text(position, end)).as_str();
+    }
+
+    /// Writes the children of `node`, except for the delimiters at either end.
+    fn delimited(&mut self, node: Node, tag: &str) {
+        let mut cursor = node.walk();
+        let delimiters: Vec<Node> = node
+            .children(&mut cursor)
+            .filter(|c| c.kind().ends_with("_delimiter"))
+            .collect();
+        let (mut start, mut end) = (node.start_byte(), node.end_byte());
+        // `**` is two delimiters, so the content starts after the first half of them.
+        let half = delimiters.len() / 2;
+        if half > 0 {
+            start = delimiters[half - 1].end_byte();
+            end = delimiters[half].start_byte();
+        }
+        self.out += format!("<{tag}>").as_str();
+        self.inline_content(node, start, end);
+        self.out += format!("</{tag}>").as_str();
+    }
+
+    fn link(&mut self, text: Node, destination: &str, title: Option<&str>) {
+        self.out += format!("<a href=\"{}\"", attribute_escape(destination)).as_str();
+        if let Some(title) = title {
+            self.out += format!(" title=\"{}\"", attribute_escape(title)).as_str();
+        }
+        self.out += ">";
+        self.inline_content(text, text.start_byte(), text.end_byte());
+        self.out += "</a>";
+    }
+
+    fn inline_node(&mut self, node: Node) {
+        let child = |kind: &str| {
+            let mut cursor = node.walk();
+            return node.named_children(&mut cursor).find(|c| c.kind() == kind);
+        };
+        match node.kind() {
+            "emphasis" => self.delimited(node, "em"),
+            "strong_emphasis" => self.delimited(node, "strong"),
+            "strikethrough" => match child("strikethrough") {
+                // `~~a~~` is parsed as a strikethrough directly inside of another one.
+                Some(inner)
+                    if inner.start_byte() == node.start_byte() + 1
+                        && inner.end_byte() + 1 == node.end_byte() =>
+                {
+                    self.inline_node(inner);
+                }
+                _ => self.delimited(node, "del"),
+            },
+            "code_span" => {
+                let code = code_span_contents(self.source, &self.inline_ranges, node);
+                self.out += format!("<code>{}</code>", html_escape(&code)).as_str();
+            }
+            "inline_link" => {
+                let destination = child("link_destination").map(|d| self.text(d));
+                let title = child("link_title").map(|t| strip_delimiters(self.text(t)));
+                match child("link_text") {
+                    Some(text) => self.link(
+                        text,
+                        strip_delimiters(destination.unwrap_or_default()),
+                        title,
+                    ),
+                    None => self.inline_content(node, node.start_byte(), node.end_byte()),
+                }
+            }
+            "full_reference_link" | "collapsed_reference_link" | "shortcut_link" => {
+                let text = child("link_text");
+                let label = match child("link_label") {
+                    Some(label) => Some(self.text(label)),
+                    None => text.map(|t| self.text(t)),
+                };
+                let reference = label.and_then(|l| self.references.get(&normalize_label(l)));
+                match (text, reference.cloned()) {
+                    (Some(text), Some((destination, title))) => {
+                        self.link(text, &destination, title.as_deref());
+                    }
+                    // without a definition, this is just text in brackets.
+                    _ => {
+                        self.out += html_escape(self.text(node)).as_str();
+                    }
+                }
+            }
+            "image" => {
+                let description = child("image_description")
+                    .map(|d| self.inline_text(d.start_byte(), d.end_byte()))
+                    .unwrap_or_default();
+                let destination = match child("link_destination") {
+                    Some(d) => Some(strip_delimiters(self.text(d)).to_string()),
+                    None => match child("link_label") {
+                        Some(label) => self
+                            .references
+                            .get(&normalize_label(self.text(label)))
+                            .map(|r| r.0.clone()),
+                        None => self
+                            .references
+                            .get(&normalize_label(&description))
+                            .map(|r| r.0.clone()),
+                    },
+                };
+                self.out += format!(
+                    "<img src=\"{}\" alt=\"{}\"",
+                    attribute_escape(&destination.unwrap_or_default()),
+                    attribute_escape(&description)
+                )
+                .as_str();
+                if let Some(title) = child("link_title") {
+                    self.out += format!(
+                        " title=\"{}\"",
+                        attribute_escape(strip_delimiters(self.text(title)))
+                    )
+                    .as_str();
+                }
+                self.out += ">";
+            }
+            "uri_autolink" | "email_autolink" => {
+                let address = strip_delimiters(self.text(node));
+                let href = if node.kind() == "email_autolink" {
+                    format!("mailto:{address}")
+                } else {
+                    address.to_string()
+                };
+                self.out += format!(
+                    "<a href=\"{}\">{}</a>",
+                    attribute_escape(&href),
+                    html_escape(address)
+                )
+                .as_str();
+            }
+            "hard_line_break" => self.out += "<br>\n",
+            "backslash_escape" => {
+                self.out += html_escape(&self.text(node)[1..]).as_str();
+            }
+            "html_tag" | "entity_reference" | "numeric_character_reference" => {
+                self.out += self.text(node);
+            }
+            // delimiters and punctuation are written as text by their parents.
+            _ if !node.is_named() => {
+                self.out +=
+                    html_escape(&self.inline_text(node.start_byte(), node.end_byte())).as_str();
+            }
+            _ => self.inline_content(node, node.start_byte(), node.end_byte()),
+        }
+    }
+}
@@ -3,10 +3,44 @@ use std::{fmt, io};
 use crate::user_config::error::MordantConfigError;
 pub type MordantResult<T> = Result<T, MordantError>;
 
+#[derive(Debug)]
 pub enum MordantError {
     Config(MordantConfigError),
     TOML(toml::de::Error),
     IO(io::Error),
+    Highlight(tree_sitter_highlight::Error),
+    UnknownLanguage(String),
+    /// tree-sitter failed to parse a markdown file.
+    Parse,
+}
+
+/// Something which was skipped, without stopping mordant from highlighting everything else.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MordantWarning {
+    /// An alias of the language `name`, which already names another language.
+    AliasTaken { alias: String, name: String },
+    /// An entry of `hl_lines` which is neither a line, nor a range of lines, in the block
+    /// starting on (1-indexed) `line`.
+    InvalidLineRange { range: String, line: usize },
+}
+
+impl fmt::Display for MordantWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AliasTaken { alias, name } => {
+                write!(
+                    f,
+                    "Ignoring alias {alias} for {name}, which is already taken."
+                )
+            }
+            Self::InvalidLineRange { range, line } => {
+                write!(
+                    f,
+                    "Ignoring invalid line range {range:?} of the block on line {line}"
+                )
+            }
+        }
+    }
 }
 
 impl From<toml::de::Error> for MordantError {
@@ -26,6 +60,12 @@ impl From<io::Error> for MordantError {
     }
 }
 
+impl From<tree_sitter_highlight::Error> for MordantError {
+    fn from(e: tree_sitter_highlight::Error) -> Self {
+        return Self::Highlight(e);
+    }
+}
+
 impl fmt::Display for MordantError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -38,6 +78,27 @@ impl fmt::Display for MordantError {
             Self::TOML(err) => {
                 write!(f, "{err}")
             }
+            Self::Highlight(err) => {
+                write!(f, "Failed to highlight: {err}")
+            }
+            Self::UnknownLanguage(language) => {
+                write!(f, "No language named {language} is configured")
+            }
+            Self::Parse => {
+                write!(f, "Failed to parse markdown")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MordantError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Config(err) => return Some(err),
+            Self::TOML(err) => return Some(err),
+            Self::IO(err) => return Some(err),
+            Self::Highlight(err) => return Some(err),
+            Self::UnknownLanguage(_) | Self::Parse => return None,
         }
     }
 }
@@ -1,29 +1,29 @@
-use super::user_config::treesitter_util::HIGHLIGHT_NAMES;
-use core::slice::Iter;
-use std::collections::BTreeMap;
+use super::document::{HtmlDocument, HtmlWriter, code_span_contents, inline_ranges};
+use super::error::{MordantError, MordantResult, MordantWarning};
+use super::info_string::InfoString;
+use super::renderer::{self, CodeBlock, Renderer, render_block};
+use super::user_config::highlighter_set::HighlighterSet;
+use super::user_config::render_options::{InlineCodeSyntax, RenderOptions};
+use std::iter;
+use std::ops::Range;
 use tree_sitter::{
-    InputEdit, Parser, Point, Query, QueryCapture, QueryCursor, StreamingIteratorMut,
+    InputEdit, Node, Parser, Point, Query, QueryCapture, QueryCursor, StreamingIteratorMut, Tree,
 };
 use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent, Highlighter};
-use tree_sitter_md;
 
-/// Gets `tree_sitter::InputEdit` for a provided (formatted) code block.
+/// Gets `tree_sitter::InputEdit` for a provided (formatted) code block, replacing `range`.
 /// TBH, I'm not really sure if this actually does anything, since we aren't tracking the offset
 /// here, or updating our treesitter `Tree`, but I have it around just in case.
-///
-/// # Panics
-///
-/// Panics if `formatted.lines().last()` is `None`.
-fn get_edit_for_block(block_capture: &QueryCapture, formatted: &String) -> InputEdit {
-    let start_byte = block_capture.node.start_byte();
-    let old_end_byte = block_capture.node.end_byte();
+fn get_edit_for_block(range: tree_sitter::Range, formatted: &String) -> InputEdit {
+    let start_byte = range.start_byte;
+    let old_end_byte = range.end_byte;
     let new_end_byte = start_byte + formatted.as_bytes().len();
 
-    let start_position = block_capture.node.start_position();
-    let old_end_position = block_capture.node.end_position();
+    let start_position = range.start_point;
+    let old_end_position = range.end_point;
 
     let new_end_row = start_position.row + formatted.lines().count();
-    let new_end_column = formatted.lines().last().unwrap().len();
+    let new_end_column = formatted.lines().last().map_or(0, str::len);
 
     let new_end_position = Point {
         row: new_end_row,
@@ -38,9 +38,59 @@ fn get_edit_for_block(block_capture: &QueryCapture, formatted: &String) -> Input
         new_end_position,
     };
 }
+
+/// Returns the `inline` and `pipe_table_cell` nodes below `node`, whose contents are parsed with
+/// `tree_sitter_md::INLINE_LANGUAGE`.
+fn inline_nodes<'t>(node: Node<'t>, nodes: &mut Vec<Node<'t>>) {
+    if node.kind() == "inline" || node.kind() == "pipe_table_cell" {
+        nodes.push(node);
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        inline_nodes(child, nodes);
+    }
+}
+
+/// Returns the `code_span` nodes below the inline `node`.
+fn code_spans<'t>(node: Node<'t>, spans: &mut Vec<Node<'t>>) {
+    if node.kind() == "code_span" {
+        spans.push(node);
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        code_spans(child, spans);
+    }
+}
+
+/// Returns the language from a pandoc style attribute at the start of `text`, e.g. `{.sql}`, and
+/// the length of the attribute.
+fn attribute_language(text: &str) -> Option<(&str, usize)> {
+    let inner = text.strip_prefix('{')?;
+    let end = inner.find(['}', '\n'])?;
+    if !inner[end..].starts_with('}') {
+        return None;
+    }
+    let language = inner[..end]
+        .split_whitespace()
+        .find_map(|class| class.strip_prefix('.'))?;
+    return Some((language, end + 2));
+}
+
+/// Splits the language from code starting with a language in braces, e.g. `{rust} Vec<T>`.
+fn prefix_language(code: &str) -> Option<(&str, &str)> {
+    let (language, code) = code.strip_prefix('{')?.split_once('}')?;
+    let language = language.trim().trim_start_matches('.');
+    if language.is_empty() || language.contains(char::is_whitespace) {
+        return None;
+    }
+    return Some((language, code.trim_start()));
+}
+
 #[derive(Debug)]
 struct CodeBlockCapture<'b> {
-    language_capture: &'b QueryCapture<'b>,
+    info_string_capture: Option<&'b QueryCapture<'b>>,
     code_block_capture: &'b QueryCapture<'b>,
     full_block_capture: &'b QueryCapture<'b>,
     file_contents: &'b String,
@@ -48,62 +98,257 @@ struct CodeBlockCapture<'b> {
 
 impl<'b> CodeBlockCapture<'b> {
     /// Creates a new [`CodeBlockCapture`].
-    /// This is specific to the query specified in [`MarkdownFile::new`], and expects
-    /// exactly 3 captures: `@block, @injection.language, @injection.content`. In that order.
-    /// Anything else is undefined behavior.
+    /// This is specific to the query specified in [`MarkdownFile::new`], and expects the
+    /// captures `@block, @info_string, @injection.content`, where `@info_string` is missing for
+    /// fences without an info string. Captures are looked up by name, since their number varies.
     ///
     /// # Panics
     //
-    /// Panics if there are less than 3 captures.
+    /// Panics if `@block` or `@injection.content` are missing.
     pub fn new(
-        captures: &'b mut Iter<QueryCapture>,
+        query: &Query,
+        captures: &'b [QueryCapture<'b>],
         file_contents: &'b String,
     ) -> CodeBlockCapture<'b> {
-        assert!(captures.len() == 3);
-        let full_block_capture = captures.next().unwrap();
-        let language_capture = captures.next().unwrap();
-        let code_block_capture = captures.next().unwrap();
+        let find = |name: &str| {
+            return captures
+                .iter()
+                .find(|c| query.capture_names()[c.index as usize] == name);
+        };
         return CodeBlockCapture {
-            language_capture,
-            code_block_capture,
-            full_block_capture,
+            info_string_capture: find("info_string"),
+            code_block_capture: find("injection.content").unwrap(),
+            full_block_capture: find("block").unwrap(),
             file_contents,
         };
     }
-    /// Returns a reference to the text captured by `@injection.language` of this [`CodeBlockCapture`].
-    pub fn language(&self) -> &str {
-        return &self.get_capture_contents(self.language_capture);
+    /// Returns a reference to the full info string of this [`CodeBlockCapture`], including
+    /// the language, or an empty string if there is none.
+    pub fn info_string(&self) -> &str {
+        match self.info_string_capture {
+            Some(capture) => return self.get_capture_contents(capture),
+            None => return "",
+        }
+    }
+    /// Returns the text captured by `@injection.content` of this [`CodeBlockCapture`], without
+    /// the prefixes of the containers the block is nested in, e.g. `> ` or list indentation.
+    pub fn code_contents(&self) -> String {
+        let node = self.code_block_capture.node;
+        let mut code = String::new();
+        let mut position = node.start_byte();
+        for continuation in self.continuations() {
+            code += &self.file_contents[position..continuation.start];
+            position = continuation.end;
+        }
+        code += &self.file_contents[position..self.code_end()];
+        return code;
+    }
+    /// Returns the end of the code. At the end of a file without a trailing newline, tree-sitter
+    /// parses the closing fence as part of `@injection.content`, so it is left out here.
+    fn code_end(&self) -> usize {
+        let node = self.code_block_capture.node;
+        let block = self.full_block_capture.node;
+        let mut cursor = block.walk();
+        let delimiters: Vec<Node> = block
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "fenced_code_block_delimiter")
+            .collect();
+        let [opening] = delimiters[..] else {
+            return node.end_byte();
+        };
+        let fence = self.node_contents(opening);
+        let line_start = self.file_contents[..node.end_byte()]
+            .rfind('\n')
+            .map_or(node.start_byte(), |i| i + 1)
+            .max(node.start_byte());
+        // the last line, without the prefixes of its containers.
+        let tail_start = self
+            .all_continuations()
+            .last()
+            .filter(|c| c.start >= line_start)
+            .map_or(line_start, |c| c.end);
+        let last_line = self.file_contents[tail_start..node.end_byte()].trim();
+        let fence_char = fence.chars().next().unwrap_or('`');
+        if last_line.len() >= fence.len() && last_line.chars().all(|c| c == fence_char) {
+            return line_start;
+        }
+        return node.end_byte();
+    }
+    /// Returns the byte ranges of the container prefixes (`block_continuation` nodes) inside of
+    /// the code, in order.
+    pub fn continuations(&self) -> Vec<Range<usize>> {
+        let end = self.code_end();
+        return self
+            .all_continuations()
+            .into_iter()
+            .filter(|c| c.start < end)
+            .collect();
+    }
+    fn all_continuations(&self) -> Vec<Range<usize>> {
+        let node = self.code_block_capture.node;
+        let mut cursor = node.walk();
+        return node
+            .named_children(&mut cursor)
+            .filter(|c| c.kind() == "block_continuation")
+            .map(|c| c.byte_range())
+            .collect();
     }
-    /// Returns a reference to the text captured by `@injection.content` of this
-    /// [`CodeBlockCapture`].
-    pub fn code_contents(&self) -> &str {
-        return &self.get_capture_contents(self.code_block_capture);
+    /// Returns the range of this capture's `@block`. Inside of containers, the block may end with
+    /// the prefix of the following line, e.g. `> `, which is not part of the returned range.
+    pub fn range(&self) -> tree_sitter::Range {
+        let node = self.full_block_capture.node;
+        let mut range = node.range();
+        if let Some(last) = node.named_child(node.named_child_count().saturating_sub(1))
+            && last.kind() == "block_continuation"
+        {
+            range.end_byte = last.start_byte();
+            range.end_point = last.start_position();
+        }
+        return range;
+    }
+    /// Returns the prefix of the lines of this block, from the start of the line of its opening
+    /// fence, e.g. `> ` inside of block quotes. List markers are replaced by spaces, so that
+    /// `- ` becomes the indentation of the item's contents.
+    pub fn prefix(&self) -> String {
+        let start = self.full_block_capture.node.start_byte();
+        let line_start = self.file_contents[..start].rfind('\n').map_or(0, |i| i + 1);
+        return self.file_contents[line_start..start]
+            .chars()
+            .map(|c| {
+                if c == '>' || c.is_whitespace() {
+                    c
+                } else {
+                    ' '
+                }
+            })
+            .collect();
     }
     /// Returns a reference to the [`QueryCapture`] for this capture's `@block`.
     pub fn full_capture(&self) -> &QueryCapture<'_> {
         return &self.full_block_capture;
     }
 
+    /// Returns the parsed info string of this [`CodeBlockCapture`].
+    pub fn attributes(&self) -> InfoString {
+        return InfoString::parse(self.info_string());
+    }
+
     /// Returns a reference to the text captured by the provided capture.
     fn get_capture_contents(&self, capture: &QueryCapture) -> &str {
-        let start = capture.node.start_byte();
-        let end = capture.node.end_byte();
-        return &self.file_contents[start..end];
+        return self.node_contents(capture.node);
+    }
+
+    /// Returns a reference to the text of `node`.
+    fn node_contents(&self, node: Node) -> &str {
+        return &self.file_contents[node.byte_range()];
+    }
+}
+
+/// Renders `block` with the highlighter `hl_cfg`, or as a single piece of text, without any
+/// highlights, if there is none.
+///
+/// # Errors
+///
+/// This function will return an error if highlighting fails.
+fn render_with(
+    renderer: &mut dyn Renderer,
+    highlighters: &HighlighterSet,
+    highlighter: &mut Highlighter,
+    hl_cfg: Option<&HighlightConfiguration>,
+    block: &CodeBlock,
+) -> Result<String, tree_sitter_highlight::Error> {
+    let Some(hl_cfg) = hl_cfg else {
+        let source = HighlightEvent::Source {
+            start: 0,
+            end: block.code.len(),
+        };
+        return render_block(renderer, block, iter::once(Ok(source)));
+    };
+    let highlights = highlighter.highlight(hl_cfg, block.code.as_bytes(), None, |lang| {
+        return highlighters.get(lang);
+    })?;
+    return render_block(renderer, block, highlights);
+}
+
+/// Highlights a snippet of `language`, rather than the code blocks of a markdown file, e.g.
+/// code read from stdin.
+///
+/// # Errors
+///
+/// This function will return an error if `language` has no highlighter, or if highlighting fails.
+pub fn render_code(
+    renderer: &mut dyn Renderer,
+    highlighters: &HighlighterSet,
+    language: &str,
+    code: &str,
+) -> MordantResult<String> {
+    let Some(hl_cfg) = highlighters.get(language) else {
+        return Err(MordantError::UnknownLanguage(language.into()));
+    };
+    let attributes = InfoString::default();
+    let block = CodeBlock {
+        language,
+        attributes: &attributes,
+        code,
+        range: 0..code.len(),
+        code_offset: 0,
+        continuations: Vec::new(),
+        lines: 1..=code.lines().count(),
+        plain: false,
+        highlighter: Some(&hl_cfg.language_name),
+        inline: false,
+    };
+    let mut highlighter = Highlighter::new();
+    return Ok(render_with(
+        renderer,
+        highlighters,
+        &mut highlighter,
+        Some(hl_cfg),
+        &block,
+    )?);
+}
+
+/// Writes `prefix` before every line of `text` but the first, e.g. to keep a block inside of a
+/// block quote. Trailing whitespace of the prefix is dropped on empty lines.
+fn with_prefix(text: &str, prefix: &str) -> String {
+    if prefix.is_empty() {
+        return text.into();
+    }
+    let mut prefixed = String::with_capacity(text.len());
+    let mut lines = text.split_inclusive('\n').peekable();
+    while let Some(line) = lines.next() {
+        prefixed += line;
+        match lines.peek() {
+            Some(next) if next.trim().is_empty() => prefixed += prefix.trim_end(),
+            Some(_) => prefixed += prefix,
+            // text ending in a newline is followed by the next line of the file, which already
+            // has its own prefix.
+            None => {}
+        }
     }
+    return prefixed;
 }
+
 #[derive(Debug)]
 pub struct BlockReplacement {
     pub input_edit: InputEdit,
     pub formatted: String,
+    /// The prefix of the lines of the replaced block, written before every line of
+    /// [`BlockReplacement::formatted`] but the first when it is applied.
+    pub prefix: String,
 }
 pub struct MarkdownFile<'a> {
     file_contents: String,
-    highlighters: &'a BTreeMap<String, HighlightConfiguration>,
+    options: &'a RenderOptions,
+    highlighters: &'a HighlighterSet,
+    renderer: Box<dyn Renderer + 'a>,
     // tree: Tree, for future use
     code_block_query: Query,
+    warnings: Vec<MordantWarning>,
 }
-impl MarkdownFile<'_> {
-    /// Creates a new [`MarkdownFile`].
+impl<'a> MarkdownFile<'a> {
+    /// Creates a new [`MarkdownFile`], which renders code blocks with the renderer for the
+    /// configured output format.
     ///
     /// # Panics
     ///
@@ -111,13 +356,13 @@ impl MarkdownFile<'_> {
     /// this.
     pub fn new(
         file_contents: String,
-        highlighters: &BTreeMap<String, HighlightConfiguration>,
-    ) -> MarkdownFile<'_> {
+        highlighters: &'a HighlighterSet,
+        options: &'a RenderOptions,
+    ) -> MarkdownFile<'a> {
         let code_block_query = tree_sitter::Query::new(
             &tree_sitter_md::LANGUAGE.into(),
             "(fenced_code_block
-              (info_string
-                (language) @injection.language)
+              (info_string)? @info_string
                   (code_fence_content) @injection.content
             ) @block"
                 .into(),
@@ -126,26 +371,52 @@ impl MarkdownFile<'_> {
 
         return MarkdownFile {
             file_contents,
+            options,
             highlighters,
+            renderer: renderer::from_options(options),
             // tree,
             code_block_query,
+            warnings: Vec::new(),
         };
     }
 
+    /// Replaces the [`Renderer`] used for code blocks.
+    pub fn with_renderer(mut self, renderer: Box<dyn Renderer + 'a>) -> Self {
+        self.renderer = renderer;
+        return self;
+    }
+
+    /// Parses the file contents with the markdown block grammar.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the provided file is not parseable.
+    fn parse(&self) -> MordantResult<Tree> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_md::LANGUAGE.into())
+            .map_err(|_| MordantError::Parse)?;
+        return parser
+            .parse(&self.file_contents, None)
+            .ok_or(MordantError::Parse);
+    }
+
     /// Gets a [`Vec<BlockReplacement>`] to apply to this [`MarkdownFile`].
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if
+    /// This function will return an error if
     /// - the provided file is not parseable.
     /// - any highlightevent results in an error.
-    pub fn get_edits(&mut self) -> Vec<BlockReplacement> {
-        let mut parser = Parser::new();
-        let _ = parser
-            .set_language(&tree_sitter_md::LANGUAGE.into())
-            .unwrap();
-        let tree = parser.parse(&self.file_contents, None).unwrap();
+    pub fn get_edits(&mut self) -> MordantResult<Vec<BlockReplacement>> {
+        let tree = self.parse()?;
+        return self.get_edits_for(&tree);
+    }
 
+    /// Gets the [`BlockReplacement`]s for the code blocks in `tree`, which must be parsed from
+    /// the current file contents.
+    fn get_edits_for(&mut self, tree: &Tree) -> MordantResult<Vec<BlockReplacement>> {
+        self.warnings.clear();
         let mut cursor = QueryCursor::new();
 
         let mut code_blocks = cursor.matches(
@@ -156,68 +427,219 @@ impl MarkdownFile<'_> {
         let mut highlighter = Highlighter::new();
         let mut edits: Vec<BlockReplacement> = Vec::new();
         while let Some(query_match) = code_blocks.next_mut() {
-            let mut captures = query_match.captures.iter();
-            let capture = CodeBlockCapture::new(&mut captures, &self.file_contents);
-            let lang = capture.language();
-            if let Some(hl_cfg) = self.highlighters.get(lang) {
-                let code_block_contents = capture.code_contents();
-                let highlights =
-                    highlighter.highlight(&hl_cfg, code_block_contents.as_bytes(), None, |lang| {
-                        return self.highlighters.get(lang);
-                    });
-                let mut formatted: String = "<pre><code>".into();
-
-                for event in highlights.unwrap() {
-                    match event.unwrap() {
-                        HighlightEvent::Source { start, end } => {
-                            formatted += format!("{}", &code_block_contents[start..end])
-                                .replace("&", "&amp;")
-                                .replace("<", "&lt;")
-                                .replace(">", "&gt;")
-                                .as_str();
-                        }
-                        HighlightEvent::HighlightStart(s) => {
-                            let classname = format!("code-{}", HIGHLIGHT_NAMES[s.0]);
-                            formatted += format!("<span class=\"{}\">", classname).as_str();
-                        }
-                        HighlightEvent::HighlightEnd => {
-                            formatted += format!("</span>").as_str();
-                        }
+            let capture = CodeBlockCapture::new(
+                &self.code_block_query,
+                query_match.captures,
+                &self.file_contents,
+            );
+            let info_string = capture.info_string();
+            let attributes = capture.attributes();
+            // the language is the first word or class, e.g. `python` for ```{.python #id}.
+            let lang = match &attributes.language {
+                Some(lang) => lang.as_str(),
+                None => self.options.default_language.as_deref().unwrap_or_default(),
+            };
+            let hl_cfg = self.highlighters.get(lang);
+            // raw blocks for pandoc, e.g. ```{=html}, are never touched.
+            let raw = info_string.starts_with("{=");
+            if hl_cfg.is_none() && (!self.options.plain_fallback || raw) {
+                continue;
+            }
+
+            let code_block_contents = capture.code_contents();
+            let node = capture.full_capture().node;
+            for range in attributes.invalid_line_ranges() {
+                self.warnings.push(MordantWarning::InvalidLineRange {
+                    range: range.into(),
+                    line: node.start_position().row + 1,
+                });
+            }
+            let range = capture.range();
+            // the block ends at the start of the line following the closing fence.
+            let end_line = match range.end_point.column {
+                0 => range.end_point.row,
+                _ => range.end_point.row + 1,
+            };
+            let block = CodeBlock {
+                language: lang,
+                attributes: &attributes,
+                code: &code_block_contents,
+                range: range.start_byte..range.end_byte,
+                code_offset: capture.code_block_capture.node.start_byte(),
+                continuations: capture.continuations(),
+                lines: node.start_position().row + 1..=end_line,
+                plain: hl_cfg.is_none(),
+                highlighter: hl_cfg.map(|c| c.language_name.as_str()),
+                inline: false,
+            };
+            let formatted = render_with(
+                self.renderer.as_mut(),
+                self.highlighters,
+                &mut highlighter,
+                hl_cfg,
+                &block,
+            )?;
+
+            let input_edit = get_edit_for_block(range, &formatted);
+            edits.push(BlockReplacement {
+                input_edit,
+                formatted,
+                prefix: capture.prefix(),
+            });
+        }
+
+        if self.options.inline_code != InlineCodeSyntax::Off && self.renderer.inline_spans() {
+            edits.extend(self.get_inline_edits_for(tree, &mut highlighter)?);
+            edits.sort_by_key(|edit| edit.input_edit.start_byte);
+        }
+        return Ok(edits);
+    }
+
+    /// Gets the [`BlockReplacement`]s for the inline code spans with a language hint in `tree`.
+    /// The replaced text includes the hint, if it is an attribute after the span.
+    fn get_inline_edits_for(
+        &mut self,
+        tree: &Tree,
+        highlighter: &mut Highlighter,
+    ) -> MordantResult<Vec<BlockReplacement>> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_md::INLINE_LANGUAGE.into())
+            .map_err(|_| MordantError::Parse)?;
+        let mut inlines = Vec::new();
+        inline_nodes(tree.root_node(), &mut inlines);
+
+        let syntax = self.options.inline_code;
+        let attributes = InfoString::default();
+        let mut edits: Vec<BlockReplacement> = Vec::new();
+        for inline in inlines {
+            let ranges = inline_ranges(inline);
+            if ranges.is_empty() {
+                continue;
+            }
+            parser
+                .set_included_ranges(&ranges)
+                .map_err(|_| MordantError::Parse)?;
+            let inline_tree = parser
+                .parse(&self.file_contents, None)
+                .ok_or(MordantError::Parse)?;
+            let mut spans = Vec::new();
+            code_spans(inline_tree.root_node(), &mut spans);
+
+            for span in spans {
+                let code = code_span_contents(&self.file_contents, &ranges, span);
+                let mut range = span.range();
+                // hints are only taken for configured languages, so that e.g. `{name}` in prose
+                // is never mistaken for one.
+                let known = |lang: &str| return self.highlighters.get(lang).is_some();
+                let (lang, code) = match attribute_language(&self.file_contents[range.end_byte..]) {
+                    Some((lang, length)) if syntax.attribute() && known(lang) => {
+                        range.end_byte += length;
+                        range.end_point.column += length;
+                        (lang.to_string(), code)
                     }
-                }
-                formatted += "\n</code></pre>\n\n";
+                    _ => match prefix_language(&code) {
+                        Some((lang, code)) if syntax.prefix() && known(lang) => {
+                            (lang.to_string(), code.into())
+                        }
+                        _ => continue,
+                    },
+                };
+                let hl_cfg = self.highlighters.get(&lang);
 
-                let input_edit = get_edit_for_block(capture.full_capture(), &formatted);
+                let row = span.start_position().row + 1;
+                let block = CodeBlock {
+                    language: &lang,
+                    attributes: &attributes,
+                    code: &code,
+                    range: range.start_byte..range.end_byte,
+                    code_offset: span.start_byte(),
+                    continuations: Vec::new(),
+                    lines: row..=row,
+                    plain: false,
+                    highlighter: hl_cfg.map(|c| c.language_name.as_str()),
+                    inline: true,
+                };
+                let formatted = render_with(
+                    self.renderer.as_mut(),
+                    self.highlighters,
+                    highlighter,
+                    hl_cfg,
+                    &block,
+                )?;
                 edits.push(BlockReplacement {
-                    input_edit,
+                    input_edit: get_edit_for_block(range, &formatted),
                     formatted,
+                    prefix: String::new(),
                 });
-            };
+            }
         }
-        return edits;
+        return Ok(edits);
     }
 
     /// Applies block replacement edits to the file, tracking offsets.
     pub fn apply_edits(&mut self, edits: Vec<BlockReplacement>) {
-        let mut offset = 0;
+        // renderers may write less than the original block, so the offset can be negative.
+        let mut offset: isize = 0;
         for edit in edits.iter() {
-            let range =
-                (edit.input_edit.start_byte + offset)..(edit.input_edit.old_end_byte + offset);
+            let range = edit
+                .input_edit
+                .start_byte
+                .checked_add_signed(offset)
+                .unwrap()
+                ..edit
+                    .input_edit
+                    .old_end_byte
+                    .checked_add_signed(offset)
+                    .unwrap();
 
-            offset += edit.formatted.len() - range.len();
-            self.file_contents
-                .replace_range(range, edit.formatted.as_str())
+            let formatted = with_prefix(&edit.formatted, &edit.prefix);
+            offset += formatted.len() as isize - range.len() as isize;
+            self.file_contents.replace_range(range, formatted.as_str())
         }
     }
 
     /// Formats the file contents inplace, replacing code blocks with html fragments.
-    pub fn format(&mut self) {
-        let edits = self.get_edits();
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can't be parsed, or highlighting fails.
+    pub fn format(&mut self) -> MordantResult<()> {
+        let edits = self.get_edits()?;
         self.apply_edits(edits);
+        return Ok(());
+    }
+
+    /// Renders the whole file to html, with code blocks written by the renderer, and everything
+    /// else from the same parse.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can't be parsed, or highlighting fails.
+    pub fn render_document(&mut self) -> MordantResult<HtmlDocument> {
+        let tree = self.parse()?;
+        let code_blocks = self
+            .get_edits_for(&tree)?
+            .into_iter()
+            .map(|edit| {
+                let input_edit = edit.input_edit;
+                return (
+                    input_edit.start_byte,
+                    (input_edit.old_end_byte, edit.formatted),
+                );
+            })
+            .collect();
+        return Ok(HtmlWriter::new(&self.file_contents, &code_blocks).write(&tree));
     }
 
     /// Returns the contents of this [`MarkdownFile`].
     pub fn contents(&self) -> String {
         return self.file_contents.clone();
     }
+
+    /// Returns what was skipped while highlighting this [`MarkdownFile`], e.g. invalid
+    /// `hl_lines`.
+    pub fn warnings(&self) -> &[MordantWarning] {
+        return &self.warnings;
+    }
 }
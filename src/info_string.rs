@@ -0,0 +1,137 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The parsed info string of a fenced code block.
+///
+/// Both ` ```python linenos ` and ` ```{python hl_lines="2-4 7"} ` are accepted. The first bare
+/// word is the language, any other bare word is a flag, and `key=value` pairs are attributes.
+/// Values may be quoted with `"` or `'` to include whitespace.
+///
+/// Pandoc style attribute blocks, e.g. ` ```{.python .numberLines #lst-setup startFrom="10"} `,
+/// are accepted as well. Without a bare word, the first class is the language, `#...` is the id,
+/// and the other classes are kept in order.
+#[derive(Debug, Default)]
+pub struct InfoString {
+    pub language: Option<String>,
+    pub flags: BTreeSet<String>,
+    pub attributes: BTreeMap<String, String>,
+    pub id: Option<String>,
+    pub classes: Vec<String>,
+}
+
+impl InfoString {
+    /// Parses the text of an `info_string` node.
+    pub fn parse(info_string: &str) -> InfoString {
+        let mut parsed = InfoString::default();
+        let inner = info_string.trim();
+        let inner = inner
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .unwrap_or(inner);
+
+        for word in split_words(inner) {
+            if let Some((key, value)) = word.split_once('=') {
+                let _ = parsed.attributes.insert(key.into(), unquote(value).into());
+            } else if let Some(id) = word.strip_prefix('#') {
+                parsed.id = Some(id.into());
+            } else if let Some(class) = word.strip_prefix('.') {
+                parsed.classes.push(class.into());
+            } else if parsed.language.is_none() && parsed.flags.is_empty() {
+                parsed.language = Some(word.into());
+            } else {
+                let _ = parsed.flags.insert(word.into());
+            }
+        }
+        if parsed.language.is_none() && !parsed.classes.is_empty() {
+            parsed.language = Some(parsed.classes.remove(0));
+        }
+        return parsed;
+    }
+
+    /// Returns whether this block should be written with line numbers.
+    /// `linenos` (or pandoc's `.numberLines`) and `nolinenos` override the provided global
+    /// default.
+    pub fn line_numbers(&self, default: bool) -> bool {
+        if self.flags.contains("linenos") || self.classes.iter().any(|c| c == "numberLines") {
+            return true;
+        }
+        if self.flags.contains("nolinenos") {
+            return false;
+        }
+        return default;
+    }
+
+    /// Returns the (1-indexed) lines selected by the `hl_lines` attribute, if any.
+    pub fn highlighted_lines(&self) -> BTreeSet<usize> {
+        match self.attributes.get("hl_lines") {
+            Some(ranges) => return parse_line_ranges(ranges).0,
+            None => return BTreeSet::new(),
+        }
+    }
+
+    /// Returns the entries of the `hl_lines` attribute which are skipped by
+    /// [`InfoString::highlighted_lines`], since they aren't lines or ranges of lines.
+    pub fn invalid_line_ranges(&self) -> Vec<&str> {
+        match self.attributes.get("hl_lines") {
+            Some(ranges) => return parse_line_ranges(ranges).1,
+            None => return Vec::new(),
+        }
+    }
+}
+
+/// Splits `text` on whitespace, except for whitespace inside of quotes.
+fn split_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut start: Option<usize> = None;
+    for (i, c) in text.char_indices() {
+        match (quote, start) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, _) if c == '"' || c == '\'' => {
+                quote = Some(c);
+                start.get_or_insert(i);
+            }
+            (None, Some(s)) if c.is_whitespace() => {
+                words.push(&text[s..i]);
+                start = None;
+            }
+            (None, None) if !c.is_whitespace() => start = Some(i),
+            (None, _) => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push(&text[s..]);
+    }
+    return words;
+}
+
+/// Strips one pair of matching quotes from `value`, if present.
+fn unquote(value: &str) -> &str {
+    for q in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(q).and_then(|v| v.strip_suffix(q)) {
+            return inner;
+        }
+    }
+    return value;
+}
+
+/// Parses line selections like `2-4 7` or `2-4,7` into the set of selected lines.
+/// Invalid entries are skipped, and returned next to the lines.
+fn parse_line_ranges(ranges: &str) -> (BTreeSet<usize>, Vec<&str>) {
+    let mut lines = BTreeSet::new();
+    let mut invalid = Vec::new();
+    for range in ranges
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|r| !r.is_empty())
+    {
+        let bounds = match range.split_once('-') {
+            Some((start, end)) => (start.parse::<usize>(), end.parse::<usize>()),
+            None => (range.parse::<usize>(), range.parse::<usize>()),
+        };
+        match bounds {
+            (Ok(start), Ok(end)) if start <= end => lines.extend(start..=end),
+            _ => invalid.push(range),
+        }
+    }
+    return (lines, invalid);
+}
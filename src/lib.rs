@@ -0,0 +1,154 @@
+//! Highlights the code blocks of markdown files with tree-sitter.
+//!
+//! [`Mordant`] holds the highlighters for every configured language, and the options for
+//! writing them out. Building the highlighters loads every grammar and query, so a [`Mordant`]
+//! should be built once, and reused for every file.
+//!
+//! ```no_run
+//! use mordant::{Mordant, MordantConfig};
+//!
+//! let config: MordantConfig = toml::from_str("[languages.python]\nname = \"python\"")?;
+//! let mordant = Mordant::new(config)?;
+//! let html = mordant.highlight_markdown("```python\nx = 1\n```\n")?;
+//! let snippet = mordant.highlight_code("py", "x = 1")?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+#[cfg(test)]
+mod tests;
+
+mod css;
+mod document;
+mod error;
+mod file_highlighter;
+mod info_string;
+mod renderer;
+mod user_config;
+
+use std::path::Path;
+
+pub use css::stylesheet;
+pub use document::{DEFAULT_TEMPLATE, HtmlDocument};
+pub use error::{MordantError, MordantResult, MordantWarning};
+pub use file_highlighter::MarkdownFile;
+pub use info_string::InfoString;
+pub use renderer::ansi::{AnsiRenderer, ColorDepth};
+pub use renderer::json::{BlockRecord, JsonRenderer, Token};
+pub use renderer::latex::preamble as latex_preamble;
+pub use renderer::rtf::RtfRenderer;
+pub use renderer::{CodeBlock, Renderer};
+pub use user_config::MordantConfig;
+pub use user_config::class_scheme::ClassScheme;
+pub use user_config::error::MordantConfigError;
+pub use user_config::highlighter_set::HighlighterSet;
+pub use user_config::render_options::{
+    DocumentOptions, FigureOptions, InlineCodeSyntax, OutputFormat, RenderOptions, SvgOptions,
+};
+pub use user_config::theme::{ColorScheme, Style, Theme, ThemeVariant};
+pub use user_config::treesitter_util::HIGHLIGHT_NAMES;
+
+/// The highlighters and render options of a [`MordantConfig`], ready to highlight any number of
+/// files. Code is written by the renderer for the configured [`OutputFormat`], html by default.
+pub struct Mordant {
+    highlighters: HighlighterSet,
+    options: RenderOptions,
+    warnings: Vec<MordantWarning>,
+}
+
+impl Mordant {
+    /// Builds the highlighters for every language in `config`.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any of the configured languages or queries can't
+    /// be loaded.
+    pub fn new(config: MordantConfig) -> MordantResult<Mordant> {
+        let options = config.render_options();
+        let (highlighters, warnings) = config.get_highlight_configurations()?;
+        return Ok(Mordant {
+            highlighters,
+            options,
+            warnings,
+        });
+    }
+
+    /// Reads the config file at `path`, and builds its highlighters.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can't be read, or [`Mordant::new`] fails.
+    pub fn from_file(path: impl AsRef<Path>) -> MordantResult<Mordant> {
+        return Mordant::new(MordantConfig::from_file(path)?);
+    }
+
+    /// Replaces the [`RenderOptions`] from the config.
+    pub fn with_options(mut self, options: RenderOptions) -> Self {
+        self.options = options;
+        return self;
+    }
+
+    pub fn options(&self) -> &RenderOptions {
+        return &self.options;
+    }
+
+    pub fn highlighters(&self) -> &HighlighterSet {
+        return &self.highlighters;
+    }
+
+    /// Returns what was skipped while loading the config, e.g. aliases which are already taken.
+    pub fn warnings(&self) -> &[MordantWarning] {
+        return &self.warnings;
+    }
+
+    /// Returns a [`MarkdownFile`] for `markdown`, e.g. to render it with a custom [`Renderer`].
+    pub fn markdown_file(&self, markdown: &str) -> MarkdownFile<'_> {
+        return MarkdownFile::new(markdown.into(), &self.highlighters, &self.options);
+    }
+
+    /// Returns `markdown` with every code block of a configured language replaced by its
+    /// highlighted version.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the markdown can't be parsed, or highlighting fails.
+    pub fn highlight_markdown(&self, markdown: &str) -> MordantResult<String> {
+        let mut file = self.markdown_file(markdown);
+        file.format()?;
+        return Ok(file.contents());
+    }
+
+    /// Renders `markdown` to html, for placing into a page with
+    /// [`HtmlDocument::fill_template`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the markdown can't be parsed, or highlighting fails.
+    pub fn render_document(&self, markdown: &str) -> MordantResult<HtmlDocument> {
+        return self.markdown_file(markdown).render_document();
+    }
+
+    /// Highlights a snippet of `language`, which may be the name of a configured language or
+    /// one of its aliases.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `language` is not configured, or highlighting fails.
+    pub fn highlight_code(&self, language: &str, code: &str) -> MordantResult<String> {
+        let mut renderer = renderer::from_options(&self.options);
+        return self.highlight_code_with(renderer.as_mut(), language, code);
+    }
+
+    /// Highlights a snippet of `language` with `renderer`, e.g. an
+    /// [`RtfRenderer`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if `language` is not configured, or highlighting fails.
+    pub fn highlight_code_with(
+        &self,
+        renderer: &mut dyn Renderer,
+        language: &str,
+        code: &str,
+    ) -> MordantResult<String> {
+        return file_highlighter::render_code(renderer, &self.highlighters, language, code);
+    }
+}
@@ -1,28 +1,126 @@
-#[cfg(test)]
-mod tests;
-
-mod error;
-pub(crate) mod file_highlighter;
-mod user_config;
-use clap::Parser;
-use error::MordantResult;
-use file_highlighter::MarkdownFile;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use ignore::WalkBuilder;
+use mordant::{
+    AnsiRenderer, ColorDepth, DEFAULT_TEMPLATE, JsonRenderer, Mordant, MordantConfig,
+    MordantResult, OutputFormat, RenderOptions, RtfRenderer, Theme, latex_preamble, stylesheet,
+};
 use rayon::prelude::*;
-use std::path::Path;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{self, Component, Path, PathBuf};
 use std::{
-    fs::{create_dir_all, read_to_string, write},
+    env,
+    fs::{
+        copy, create_dir_all, metadata, read_to_string, remove_file, rename, set_permissions, write,
+    },
     process::ExitCode,
 };
-use user_config::MordantConfig;
+
+/// The last line of files highlighted in place, so that they are never highlighted twice.
+const IN_PLACE_MARKER: &str = "<!-- highlighted by mordant -->";
+
+#[cfg(test)]
+#[path = "tests/cli.rs"]
+mod tests;
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Markdown files to highlight. Directories are searched recursively, skipping anything
+    /// ignored by `.gitignore` or `.mordantignore`. `-`, or no files at all, reads from stdin and
+    /// writes the highlighted document to stdout.
     #[arg(num_args=1..)]
     file: Vec<String>,
-    #[arg(long, short, default_value_t = String::from("./mordant.toml"))]
+    /// Extensions of the files highlighted when searching directories.
+    #[arg(long, value_delimiter = ',', default_values_t = ["md", "markdown", "mdx"].map(String::from))]
+    extensions: Vec<String>,
+    #[arg(long, short, global = true, default_value_t = String::from("./mordant.toml"))]
     config_file: String,
     #[arg(long, short, default_value_t = String::from("./mordant.out"))]
     output_dir: String,
+    /// Directory the input files are written relative to, e.g. `site/posts/a.md` is written to
+    /// `<OUTPUT_DIR>/posts/a.md` with `--input-root site`. Files outside of it are skipped.
+    #[arg(long, visible_alias = "strip-prefix", default_value_t = String::from("."))]
+    input_root: String,
+    /// What code blocks are turned into. Overrides `format` from the config file.
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+    /// Write complete html pages instead of markdown. Overrides `standalone` from the
+    /// `[document]` table of the config file.
+    #[arg(long)]
+    standalone: bool,
+    /// Rewrite the input files, instead of writing to the output directory. Files which were
+    /// already rewritten by mordant are skipped.
+    #[arg(long, short)]
+    in_place: bool,
+    /// Keep a copy of every rewritten file, with this suffix appended to its name.
+    #[arg(long, requires = "in_place")]
+    backup_suffix: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate a stylesheet covering every highlight name from a theme.
+    Css {
+        /// Theme file to read. Defaults to the `[theme]` table of the config file, or
+        /// example-theme.toml if that is empty.
+        #[arg(long, short)]
+        theme: Option<String>,
+        /// Where to write the stylesheet. Defaults to stdout.
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+    /// Generate the LaTeX preamble for blocks written with `--format latex` from a theme.
+    LatexPreamble {
+        /// Theme file to read. Defaults to the `[theme]` table of the config file, or
+        /// example-theme.toml if that is empty.
+        #[arg(long, short)]
+        theme: Option<String>,
+        /// Where to write the preamble. Defaults to stdout.
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+    /// Highlight a snippet as RTF, for pasting into office documents.
+    Rtf {
+        /// The language of the snippet, as configured in `[languages]`.
+        #[arg(long, short)]
+        language: String,
+        /// File to read the snippet from. Defaults to stdin.
+        file: Option<String>,
+        /// Theme file to read. Defaults to the `[theme]` table of the config file, or
+        /// example-theme.toml if that is empty.
+        #[arg(long, short)]
+        theme: Option<String>,
+        #[arg(long, default_value_t = String::from("Courier New"))]
+        font: String,
+        /// Font size in points.
+        #[arg(long, default_value_t = 12)]
+        font_size: usize,
+        /// Where to write the document. Defaults to stdout.
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+    /// Print markdown files to the terminal, with code blocks highlighted.
+    Cat {
+        #[arg(num_args = 1..)]
+        files: Vec<String>,
+        /// Theme file to read. Defaults to the `[theme]` table of the config file, or
+        /// example-theme.toml if that is empty.
+        #[arg(long, short)]
+        theme: Option<String>,
+        /// When to highlight. `auto` only highlights if stdout is a terminal, and `NO_COLOR` is unset.
+        #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+        color: ColorChoice,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
 }
 
 fn main() -> ExitCode {
@@ -36,19 +134,257 @@ fn main() -> ExitCode {
 
 fn run() -> MordantResult<()> {
     let args = Args::parse();
-    let config_path = Path::new(&args.config_file);
-    let mut config: MordantConfig = toml::from_str(read_to_string(config_path)?.as_str())?;
-    config = config.with_base_dir(config_path.parent().unwrap().canonicalize().unwrap().into());
-    let highlighters = config.get_highlight_configurations()?;
+    match &args.command {
+        Some(Command::Css { theme, output }) => return run_css(&args, theme, output),
+        Some(Command::LatexPreamble { theme, output }) => {
+            return run_latex_preamble(&args, theme, output);
+        }
+        Some(Command::Rtf {
+            language,
+            file,
+            theme,
+            font,
+            font_size,
+            output,
+        }) => return run_rtf(&args, language, file, theme, font, *font_size, output),
+        Some(Command::Cat {
+            files,
+            theme,
+            color,
+        }) => return run_cat(&args, files, theme, *color),
+        None => return run_highlight(&args),
+    }
+}
+
+/// Returns the render options from the config file, and the theme from either `theme`,
+/// or the config file, falling back to the builtin theme if it has no styles.
+fn load_theme(args: &Args, theme: &Option<String>) -> MordantResult<(RenderOptions, Theme)> {
+    // a standalone theme doesn't need a config file, but still respects its other options.
+    let render_options = if theme.is_none() || Path::new(&args.config_file).exists() {
+        MordantConfig::from_file(&args.config_file)?.render_options()
+    } else {
+        RenderOptions::default()
+    };
+    let theme = resolve_theme(theme, &render_options)?;
+    return Ok((render_options, theme));
+}
+
+/// Returns the theme from the `--theme` file if there is one, or else from `render_options`.
+fn resolve_theme(theme: &Option<String>, render_options: &RenderOptions) -> MordantResult<Theme> {
+    match theme {
+        Some(theme_file) => return Ok(toml::from_str(read_to_string(theme_file)?.as_str())?),
+        None => return Ok(render_options.theme_or_builtin()),
+    }
+}
+
+/// Writes `contents` to stdout. A closed stdout, e.g. when piping into `head`, is not an error.
+fn write_stdout(contents: &str) -> MordantResult<()> {
+    return write_to(io::stdout().lock(), contents);
+}
+
+/// Writes `contents` to `output`, ignoring a closed pipe, e.g. when piping into `head`.
+fn write_to(mut output: impl Write, contents: &str) -> MordantResult<()> {
+    match output
+        .write_all(contents.as_bytes())
+        .and_then(|_| output.flush())
+    {
+        Ok(_) => return Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+        Err(e) => return Err(e.into()),
+    }
+}
+
+/// Writes `contents` to `output`, or stdout if there is none.
+fn write_output(output: &Option<String>, contents: String) -> MordantResult<()> {
+    match output {
+        Some(output_file) => write(output_file, contents)?,
+        None => write_stdout(&contents)?,
+    }
+    return Ok(());
+}
+
+/// Warns about the highlight names `theme` has no style for, which get no rule in its stylesheet.
+fn warn_missing_styles(theme: &Theme) {
+    let missing = theme.missing_styles();
+    if !missing.is_empty() {
+        eprintln!(
+            "The theme has no style for {} highlight names, or any of their parents: {}",
+            missing.len(),
+            missing.join(", ")
+        );
+    }
+}
+
+/// Builds the [`Mordant`] of the config file, reporting what was skipped while loading it.
+fn load_mordant(args: &Args) -> MordantResult<Mordant> {
+    let mordant = Mordant::from_file(&args.config_file)?;
+    for warning in mordant.warnings() {
+        eprintln!("{warning}");
+    }
+    return Ok(mordant);
+}
+
+fn run_css(args: &Args, theme: &Option<String>, output: &Option<String>) -> MordantResult<()> {
+    let (render_options, theme) = load_theme(args, theme)?;
+    warn_missing_styles(&theme);
+    let stylesheet = stylesheet(
+        &theme,
+        &render_options.class_scheme,
+        &render_options.class_prefix,
+    );
+    return write_output(output, stylesheet);
+}
+
+fn run_latex_preamble(
+    args: &Args,
+    theme: &Option<String>,
+    output: &Option<String>,
+) -> MordantResult<()> {
+    let (_, theme) = load_theme(args, theme)?;
+    return write_output(output, latex_preamble(&theme));
+}
+
+fn run_rtf(
+    args: &Args,
+    language: &str,
+    file: &Option<String>,
+    theme: &Option<String>,
+    font: &str,
+    font_size: usize,
+    output: &Option<String>,
+) -> MordantResult<()> {
+    let mordant = load_mordant(args)?;
+    let theme = resolve_theme(theme, mordant.options())?;
+
+    let code = match file {
+        Some(file) => read_to_string(file)?,
+        None => io::read_to_string(io::stdin())?,
+    };
+    // a trailing newline would end the snippet with an empty line.
+    let code = code.trim_end_matches('\n');
+    let mut renderer = RtfRenderer::new(&theme, font.into(), font_size);
+    let rtf = mordant.highlight_code_with(&mut renderer, language, code)?;
+    return write_output(output, rtf);
+}
+
+fn run_cat(
+    args: &Args,
+    files: &[String],
+    theme: &Option<String>,
+    color: ColorChoice,
+) -> MordantResult<()> {
+    let highlight = match color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+    };
+    let mordant = load_mordant(args)?;
+    let theme = resolve_theme(theme, mordant.options())?;
+    let depth = ColorDepth::detect();
 
-    let _ = &args.file.par_iter().for_each(|f| {
+    let mut stdout = io::stdout().lock();
+    for f in files {
+        let mut contents = read_to_string(f)?;
+        if highlight {
+            let mut file = mordant
+                .markdown_file(&contents)
+                .with_renderer(Box::new(AnsiRenderer::new(theme.clone(), depth)));
+            file.format()?;
+            contents = file.contents();
+        }
+        match stdout.write_all(contents.as_bytes()) {
+            Ok(_) => {}
+            // e.g. piping into `head`.
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    return Ok(());
+}
+
+fn run_highlight(args: &Args) -> MordantResult<()> {
+    let mordant = load_mordant(args)?;
+    let mut render_options = mordant.options().clone();
+    if let Some(format) = args.format {
+        render_options.format = format;
+    }
+    render_options.document.standalone |= args.standalone;
+    let mordant = mordant.with_options(render_options);
+    let render_options = mordant.options();
+    let standalone = render_options.document.standalone;
+    if args.in_place && (render_options.format == OutputFormat::Json || standalone) {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--in-place only works for markdown output, not json records or standalone pages",
+            )
+            .exit();
+    }
+
+    let document = &render_options.document;
+    let template = match &document.template {
+        Some(template_file) if document.standalone => read_to_string(template_file)?,
+        _ => DEFAULT_TEMPLATE.into(),
+    };
+    let stylesheet = document.embed_stylesheet.then(|| {
+        let theme = render_options.theme_or_builtin();
+        warn_missing_styles(&theme);
+        return stylesheet(
+            &theme,
+            &render_options.class_scheme,
+            &render_options.class_prefix,
+        );
+    });
+
+    let highlight = |contents: &str, name: Option<&str>| {
+        return highlight_markdown(&mordant, contents, name, &template, stylesheet.as_deref());
+    };
+
+    let (read_stdin, files) = stdin_and_files(&args.file);
+    if read_stdin {
+        highlight_stdio(highlight, io::stdin(), io::stdout().lock())?;
+    }
+    let files = markdown_files(&files, &args.extensions, Path::new(&args.output_dir));
+
+    files.par_iter().for_each(|f| {
         if let Ok(file_contents) = read_to_string(f) {
-            let mut file = MarkdownFile::new(file_contents, &highlighters);
-            file.format();
+            if args.in_place {
+                if highlighted_in_place(&file_contents) {
+                    eprintln!("Skipping {}, which was already highlighted by mordant", f);
+                    return;
+                }
+                let result = highlight(&file_contents, Some(f)).and_then(|(contents, _)| {
+                    let backup_suffix = args.backup_suffix.as_deref();
+                    return write_in_place(Path::new(f), contents, backup_suffix);
+                });
+                if let Err(e) = result {
+                    eprintln!("Couldn't highlight {} in place: {}", f, e);
+                }
+                return;
+            }
+            let Some(mut out_path) = output_path(
+                Path::new(&args.output_dir),
+                Path::new(&args.input_root),
+                Path::new(f),
+            ) else {
+                eprintln!("Skipping {}, which is outside of {}", f, args.input_root);
+                return;
+            };
+            let contents = match highlight(&file_contents, Some(f)) {
+                Ok((contents, extension)) => {
+                    if let Some(extension) = extension {
+                        let _ = out_path.set_extension(extension);
+                    }
+                    contents
+                }
+                Err(e) => {
+                    eprintln!("Couldn't highlight {}: {}", f, e);
+                    return;
+                }
+            };
 
-            let out_path = Path::new(&args.output_dir).join(f.clone());
             create_dir_all(&out_path.parent().unwrap()).unwrap();
-            if let Ok(_) = write(&out_path, &file.contents()) {
+            if let Ok(_) = write(&out_path, &contents) {
             } else {
                 eprintln!("Couldn't write {:?}", &out_path);
             }
@@ -59,3 +395,167 @@ fn run() -> MordantResult<()> {
 
     return Ok(());
 }
+
+/// Splits the file arguments into whether stdin is read, and the remaining paths.
+/// `-`, or no files at all, reads from stdin.
+fn stdin_and_files(files: &[String]) -> (bool, Vec<&str>) {
+    let (stdin, files): (Vec<&str>, Vec<&str>) =
+        files.iter().map(String::as_str).partition(|f| *f == "-");
+    return (files.is_empty() || !stdin.is_empty(), files);
+}
+
+/// Highlights all of `input`, and writes only the highlighted document to `output`.
+fn highlight_stdio(
+    highlight: impl Fn(&str, Option<&str>) -> MordantResult<(String, Option<&'static str>)>,
+    input: impl Read,
+    output: impl Write,
+) -> MordantResult<()> {
+    let (contents, _) = highlight(&io::read_to_string(input)?, None)?;
+    return write_to(output, &contents);
+}
+
+/// Replaces every directory in `paths` with the files below it ending in one of `extensions`.
+/// Symlinks are followed, and files ignored by `.gitignore` or `.mordantignore`, or inside of
+/// `output_dir`, are left out.
+fn markdown_files(paths: &[&str], extensions: &[String], output_dir: &Path) -> Vec<String> {
+    let output_dir =
+        path::absolute(output_dir).map_or(PathBuf::new(), |dir| return normalize(&dir));
+    let mut files = Vec::new();
+    for path in paths {
+        if !Path::new(path).is_dir() {
+            files.push(path.to_string());
+            continue;
+        }
+        let output_dir = output_dir.clone();
+        let walk = WalkBuilder::new(path)
+            .follow_links(true)
+            .require_git(false)
+            .add_custom_ignore_filename(".mordantignore")
+            .filter_entry(move |entry| {
+                let path = path::absolute(entry.path()).map(|path| return normalize(&path));
+                return path.is_ok_and(|path| return path != output_dir);
+            })
+            .build();
+        for entry in walk {
+            let entry = match entry {
+                Ok(entry) => entry,
+                // e.g. symlink loops, or unreadable directories.
+                Err(e) => {
+                    eprintln!("Couldn't search {}: {}", path, e);
+                    continue;
+                }
+            };
+            let is_markdown = entry.path().extension().is_some_and(|extension| {
+                return extensions
+                    .iter()
+                    .any(|e| return extension == e.trim_start_matches('.'));
+            });
+            if is_markdown && entry.file_type().is_some_and(|t| return t.is_file()) {
+                files.push(entry.path().to_string_lossy().into_owned());
+            }
+        }
+    }
+    return files;
+}
+
+/// Returns where `file` is written to, i.e. its path relative to `input_root`, under
+/// `output_dir`. Returns `None` if `file` is not inside `input_root`, since it would end up outside
+/// of `output_dir`, or overwrite `file` itself if it is absolute.
+fn output_path(output_dir: &Path, input_root: &Path, file: &Path) -> Option<PathBuf> {
+    let root = normalize(&path::absolute(input_root).ok()?);
+    let file = normalize(&path::absolute(file).ok()?);
+    let relative = file.strip_prefix(root).ok()?;
+    if relative.as_os_str().is_empty() {
+        return None;
+    }
+    return Some(output_dir.join(relative));
+}
+
+/// Resolves `.` and `..` in `path`, without following symlinks.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                let _ = normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    return normalized;
+}
+
+/// Whether `contents` were written by [`write_in_place`], i.e. end with the [`IN_PLACE_MARKER`].
+/// The marker anywhere else, e.g. in documentation quoting it, doesn't count.
+fn highlighted_in_place(contents: &str) -> bool {
+    return contents.trim_end().lines().last() == Some(IN_PLACE_MARKER);
+}
+
+/// Replaces the file at `path` with `contents`, followed by the [`IN_PLACE_MARKER`]. The contents
+/// are written to a temporary file next to it first, which is then renamed, so that `path` is
+/// never left half written. With a `backup_suffix`, the original is kept as `path` + suffix.
+///
+/// # Errors
+///
+/// This function will return an error if any of the files can't be written. The original file
+/// is left untouched in that case.
+fn write_in_place(
+    path: &Path,
+    mut contents: String,
+    backup_suffix: Option<&str>,
+) -> MordantResult<()> {
+    if !contents.ends_with('\n') {
+        contents += "\n";
+    }
+    contents += format!("\n{}\n", IN_PLACE_MARKER).as_str();
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.mordant-tmp", file_name));
+    let result = write(&temp_path, contents)
+        .and_then(|_| {
+            // the new file keeps the permissions of the original.
+            return set_permissions(&temp_path, metadata(path)?.permissions());
+        })
+        .and_then(|_| {
+            if let Some(suffix) = backup_suffix {
+                let mut backup_path = PathBuf::from(path);
+                backup_path.as_mut_os_string().push(suffix);
+                let _ = copy(path, backup_path)?;
+            }
+            return rename(&temp_path, path);
+        });
+    if result.is_err() {
+        let _ = remove_file(&temp_path);
+    }
+    return Ok(result?);
+}
+
+/// Highlights the markdown file `name` (`None` for stdin) in the configured format, and returns
+/// the output, along with its file extension if it isn't markdown.
+fn highlight_markdown(
+    mordant: &Mordant,
+    contents: &str,
+    name: Option<&str>,
+    template: &str,
+    stylesheet: Option<&str>,
+) -> MordantResult<(String, Option<&'static str>)> {
+    let mut file = mordant.markdown_file(contents);
+    let options = mordant.options();
+    let highlighted = if options.format == OutputFormat::Json {
+        // only the records of the blocks are written, one per line.
+        file = file.with_renderer(Box::new(JsonRenderer::new(name.map(String::from))));
+        let records = file.get_edits()?.into_iter().map(|e| e.formatted).collect();
+        (records, Some("jsonl"))
+    } else if options.document.standalone {
+        let document = file.render_document()?;
+        (document.fill_template(template, stylesheet), Some("html"))
+    } else {
+        file.format()?;
+        (file.contents(), None)
+    };
+    for warning in file.warnings() {
+        eprintln!("{}: {}", name.unwrap_or("<stdin>"), warning);
+    }
+    return Ok(highlighted);
+}
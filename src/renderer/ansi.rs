@@ -0,0 +1,145 @@
+use super::{CodeBlock, Renderer};
+use crate::user_config::theme::{Style, Theme, parse_hex};
+use std::env;
+
+/// The colors a terminal supports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorDepth {
+    /// 24-bit colors, e.g. `\x1b[38;2;251;73;52m`.
+    TrueColor,
+    /// The xterm 256 color palette, e.g. `\x1b[38;5;203m`.
+    Ansi256,
+}
+
+impl ColorDepth {
+    /// Detects the color depth of the terminal from `COLORTERM`, falling back to 256 colors.
+    pub fn detect() -> ColorDepth {
+        match env::var("COLORTERM").as_deref() {
+            Ok("truecolor") | Ok("24bit") => return ColorDepth::TrueColor,
+            _ => return ColorDepth::Ansi256,
+        }
+    }
+}
+
+/// Returns the closest color of the xterm 256 color palette, from either the 6x6x6 color cube
+/// or the grayscale ramp.
+fn ansi256((r, g, b): (u8, u8, u8)) -> u8 {
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest_level = |c: u8| {
+        return (0..6)
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - c as i32).abs())
+            .unwrap();
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        return (r as i32 - r2 as i32).pow(2)
+            + (g as i32 - g2 as i32).pow(2)
+            + (b as i32 - b2 as i32).pow(2);
+    };
+
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+    let cube_index = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (r as usize + g as usize + b as usize) / 3;
+    let gray_index = (average.saturating_sub(8) / 10).min(23);
+    let gray_level = (8 + 10 * gray_index) as u8;
+
+    if distance((gray_level, gray_level, gray_level)) < distance(cube) {
+        return (232 + gray_index) as u8;
+    }
+    return cube_index as u8;
+}
+
+/// Renders code blocks with ANSI escape codes, for previewing highlights in a terminal.
+/// The fences are kept (dimmed), so that the output still reads like the original markdown.
+pub struct AnsiRenderer {
+    theme: Theme,
+    depth: ColorDepth,
+    /// escape sequences of the currently open highlights.
+    open_styles: Vec<String>,
+}
+
+impl AnsiRenderer {
+    pub fn new(theme: Theme, depth: ColorDepth) -> AnsiRenderer {
+        return AnsiRenderer {
+            theme,
+            depth,
+            open_styles: Vec::new(),
+        };
+    }
+
+    /// Returns the SGR parameters selecting `color` as foreground (`base` 38) or
+    /// background (`base` 48).
+    fn color_parameters(&self, color: &str, base: u8) -> Option<String> {
+        let rgb = parse_hex(&self.theme.resolve_color(color))?;
+        match self.depth {
+            ColorDepth::TrueColor => {
+                return Some(format!("{base};2;{};{};{}", rgb.0, rgb.1, rgb.2));
+            }
+            ColorDepth::Ansi256 => return Some(format!("{base};5;{}", ansi256(rgb))),
+        }
+    }
+
+    /// Returns the escape sequence for `style`, or an empty string if it sets nothing.
+    fn escape_sequence(&self, style: &Style) -> String {
+        let mut parameters: Vec<String> = Vec::new();
+        if style.bold {
+            parameters.push("1".into());
+        }
+        if style.italic {
+            parameters.push("3".into());
+        }
+        if style.underline {
+            parameters.push("4".into());
+        }
+        if style.strikethrough {
+            parameters.push("9".into());
+        }
+        if let Some(color) = &style.color {
+            parameters.extend(self.color_parameters(color, 38));
+        }
+        if let Some(background) = &style.background {
+            parameters.extend(self.color_parameters(background, 48));
+        }
+        if parameters.is_empty() {
+            return "".into();
+        }
+        return format!("\x1b[{}m", parameters.join(";"));
+    }
+}
+
+impl Renderer for AnsiRenderer {
+    fn block_start(&mut self, out: &mut String, block: &CodeBlock) {
+        self.open_styles.clear();
+        *out += format!("\x1b[2m```{}\x1b[0m\n", block.language).as_str();
+    }
+
+    fn source(&mut self, out: &mut String, text: &str) {
+        *out += text;
+    }
+
+    fn highlight_start(&mut self, out: &mut String, highlight_name: &str) {
+        let sequence = match self.theme.style_for(highlight_name) {
+            Some(style) => self.escape_sequence(style),
+            None => "".into(),
+        };
+        *out += sequence.as_str();
+        self.open_styles.push(sequence);
+    }
+
+    fn highlight_end(&mut self, out: &mut String) {
+        // there is no way to pop a style, so reset and reapply the enclosing highlights.
+        if !self.open_styles.pop().unwrap_or_default().is_empty() {
+            *out += "\x1b[0m";
+            *out += self.open_styles.concat().as_str();
+        }
+    }
+
+    fn block_end(&mut self, out: &mut String, block: &CodeBlock) {
+        *out += "\x1b[0m";
+        if !block.code.ends_with('\n') {
+            *out += "\n";
+        }
+        *out += "\x1b[2m```\x1b[0m\n";
+    }
+}
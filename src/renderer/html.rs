@@ -0,0 +1,314 @@
+use super::svg::SvgRenderer;
+use super::{CodeBlock, Renderer};
+use crate::user_config::render_options::RenderOptions;
+use crate::user_config::theme::Theme;
+use std::collections::BTreeSet;
+
+/// Escapes `&`, `<` and `>` so that `text` can be placed inside an html element.
+pub fn html_escape(text: &str) -> String {
+    return text
+        .replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;");
+}
+
+/// Escapes `text` for use inside a double quoted attribute.
+pub fn attribute_escape(text: &str) -> String {
+    return html_escape(text).replace('"', "&quot;");
+}
+
+/// Returns ` class="..."` for the provided classes, or nothing if there are none.
+fn class_attribute(classes: &[String]) -> String {
+    if classes.is_empty() {
+        return "".into();
+    }
+    return format!(" class=\"{}\"", attribute_escape(&classes.join(" ")));
+}
+
+/// Returns the `id` and `data-*` attributes of a block's `<pre>`, from a pandoc style info string,
+/// e.g. ` id="lst-setup" data-startFrom="10"` for ` ```{.python #lst-setup startFrom="10"} `.
+/// `hl_lines` and `title` are used by the renderer itself, and keys which aren't valid attribute names are
+/// skipped.
+fn pre_attributes(block: &CodeBlock) -> String {
+    let mut attributes = String::new();
+    if let Some(id) = &block.attributes.id {
+        attributes += format!(" id=\"{}\"", attribute_escape(id)).as_str();
+    }
+    for (key, value) in block.attributes.attributes.iter() {
+        let valid = key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if key == "hl_lines" || key == "title" || !valid {
+            continue;
+        }
+        attributes += format!(" data-{}=\"{}\"", key, attribute_escape(value)).as_str();
+    }
+    return attributes;
+}
+
+/// The default [`Renderer`], writing each block as a `<pre><code>` html fragment.
+///
+/// When writing line numbers or highlighting lines, every line is wrapped in its own
+/// `<span class="code-line">`, where `code-` is the configured `class_prefix`. Highlight spans which are open at the end of a
+/// line are closed before the line ends, and reopened at the start of the next one, so that
+/// every line is well formed on its own. Line numbers are rendered by css from the
+/// `data-line-number` attribute, so they are never part of the copied text.
+///
+/// Blocks with the `svg` flag in their info string are written as images by an [`SvgRenderer`].
+/// Inline code spans are written as `<code>`, without `<pre>` or lines. Blocks with a `title`
+/// attribute are wrapped in a figure from the `[figure]` template.
+pub struct HtmlRenderer<'a> {
+    options: &'a RenderOptions,
+    /// the theme of inline styles, if `inline_styles` is set.
+    theme: Option<Theme>,
+    line_numbers: bool,
+    highlighted_lines: BTreeSet<usize>,
+    /// attributes of the currently open highlight spans, e.g. `class="code-keyword"`.
+    /// Highlights without any attributes are tracked, but not written.
+    open_spans: Vec<String>,
+    line_open: bool,
+    line_number: usize,
+    /// the renderer for the current block, if it has the `svg` flag.
+    svg: Option<SvgRenderer<'a>>,
+    /// the rest of the figure template after `{{code}}`, if the current block is in a figure.
+    figure_end: Option<String>,
+    /// where the code of the current figure starts in the output.
+    code_start: usize,
+}
+
+impl HtmlRenderer<'_> {
+    pub fn new(options: &RenderOptions) -> HtmlRenderer<'_> {
+        return HtmlRenderer {
+            options,
+            theme: options
+                .inline_styles
+                .then(|| return options.theme_or_builtin()),
+            line_numbers: false,
+            highlighted_lines: BTreeSet::new(),
+            open_spans: Vec::new(),
+            line_open: false,
+            line_number: 0,
+            svg: None,
+            figure_end: None,
+            code_start: 0,
+        };
+    }
+
+    /// Writes the part of the figure template before `{{code}}`, if `block` has a `title` or
+    /// badges are enabled, and keeps the rest for [`Renderer::block_end`].
+    fn open_figure(&mut self, out: &mut String, block: &CodeBlock) {
+        let options = &self.options.figure;
+        let title = block.attributes.attributes.get("title");
+        let badge = match block.highlighter {
+            Some(name) if options.badge => options
+                .badge_template
+                .replace("{{language}}", &html_escape(name)),
+            _ => "".into(),
+        };
+        if title.is_none() && badge.is_empty() {
+            return;
+        }
+        let language = block.highlighter.unwrap_or(block.language);
+        let figure = options
+            .template
+            .replace("{{title}}", &html_escape(title.map_or("", |t| t.as_str())))
+            .replace("{{badge}}", &badge)
+            .replace("{{language}}", &html_escape(language));
+        let (start, end) = figure.split_once("{{code}}").unwrap_or((&figure, ""));
+        *out += start;
+        self.figure_end = Some(end.into());
+        self.code_start = out.len();
+    }
+
+    /// Closes the figure opened by [`HtmlRenderer::open_figure`]. Markdown ends html blocks like
+    /// `<figure>` at the first blank line, so blank lines of the code get an empty span.
+    fn close_figure(&mut self, out: &mut String, end: &str) {
+        let mut code = out.split_off(self.code_start);
+        code.truncate(code.trim_end_matches('\n').len());
+        while code.contains("\n\n") {
+            code = code.replace("\n\n", "\n<span></span>\n");
+        }
+        *out += code.as_str();
+        *out += end;
+        *out += "\n\n";
+    }
+
+    /// Returns the attributes for a span highlighting `highlight_name`: either classes from the
+    /// configured [`ClassScheme`], or a `style` from the theme when writing inline styles.
+    /// Inline styles use [`RenderOptions::theme_or_builtin`], so that a config without a
+    /// `[theme]` still writes colors.
+    ///
+    /// [`ClassScheme`]: crate::user_config::class_scheme::ClassScheme
+    fn span_attributes(&self, highlight_name: &str) -> String {
+        let Some(theme) = &self.theme else {
+            let classes = self
+                .options
+                .class_scheme
+                .classes(&self.options.class_prefix, highlight_name);
+            if classes.is_empty() {
+                return "".into();
+            }
+            return format!("class=\"{}\"", attribute_escape(&classes));
+        };
+        match theme.inline_css(highlight_name) {
+            Some(css) => return format!("style=\"{}\"", attribute_escape(&css)),
+            None => return "".into(),
+        }
+    }
+
+    /// Whether every line is wrapped in its own element.
+    fn per_line(&self) -> bool {
+        return self.line_numbers || !self.highlighted_lines.is_empty();
+    }
+
+    fn open_line(&mut self, out: &mut String) {
+        if self.line_open {
+            return;
+        }
+        self.line_number += 1;
+        let prefix = &self.options.class_prefix;
+        *out += format!("<span class=\"{prefix}line").as_str();
+        if self.highlighted_lines.contains(&self.line_number) {
+            *out += format!(" {prefix}line-highlighted").as_str();
+        }
+        *out += "\"";
+        if self.line_numbers {
+            *out += format!(" data-line-number=\"{}\"", self.line_number).as_str();
+        }
+        *out += ">";
+        for attributes in self.open_spans.iter().filter(|a| !a.is_empty()) {
+            *out += format!("<span {}>", attributes).as_str();
+        }
+        self.line_open = true;
+    }
+
+    fn close_line(&mut self, out: &mut String) {
+        if !self.line_open {
+            return;
+        }
+        let written_spans = self.open_spans.iter().filter(|a| !a.is_empty()).count();
+        *out += "</span>".repeat(written_spans + 1).as_str();
+        self.line_open = false;
+    }
+}
+
+impl Renderer for HtmlRenderer<'_> {
+    fn block_start(&mut self, out: &mut String, block: &CodeBlock) {
+        self.svg = None;
+        self.figure_end = None;
+        if !block.inline {
+            self.open_figure(out, block);
+        }
+        if block.attributes.flags.contains("svg") {
+            let mut svg = SvgRenderer::new(self.options);
+            svg.block_start(out, block);
+            self.svg = Some(svg);
+            return;
+        }
+        self.line_numbers = block.attributes.line_numbers(self.options.line_numbers);
+        self.highlighted_lines = block.attributes.highlighted_lines();
+        self.open_spans.clear();
+        self.line_open = false;
+        self.line_number = 0;
+
+        // fences without an info string (and no `default_language`) have no language.
+        let language = match block.language {
+            "" => "plain",
+            language => language,
+        };
+        let (pre_class, code_class) = self.options.class_scheme.block_classes(language);
+        let pre_classes: Vec<String> = pre_class.into_iter().collect();
+        let mut code_classes: Vec<String> = code_class.into_iter().collect();
+        if block.plain {
+            code_classes.push(format!("{}plain", self.options.class_prefix));
+        }
+        if block.inline {
+            // spans are always a single line, in a `<code>` without `<pre>`.
+            self.line_numbers = false;
+            self.highlighted_lines.clear();
+            // the classes of `<pre>` go onto the `<code>`, since stylesheets may depend on them.
+            let mut classes = pre_classes;
+            for class in code_classes {
+                if !classes.contains(&class) {
+                    classes.push(class);
+                }
+            }
+            *out += format!("<code{}>", class_attribute(&classes)).as_str();
+            return;
+        }
+        if self.line_numbers {
+            code_classes.push(format!("{}line-numbers", self.options.class_prefix));
+        }
+        *out += format!(
+            "<pre{}{}><code{}>",
+            class_attribute(&[pre_classes, block.attributes.classes.clone()].concat()),
+            pre_attributes(block),
+            class_attribute(&code_classes)
+        )
+        .as_str();
+    }
+
+    fn source(&mut self, out: &mut String, text: &str) {
+        if let Some(svg) = &mut self.svg {
+            return svg.source(out, text);
+        }
+        if !self.per_line() {
+            *out += html_escape(text).as_str();
+            return;
+        }
+        let mut segments = text.split('\n').peekable();
+        while let Some(segment) = segments.next() {
+            let ends_line = segments.peek().is_some();
+            // don't start a new line for the empty text after a trailing newline.
+            if !segment.is_empty() || ends_line {
+                self.open_line(out);
+            }
+            *out += html_escape(segment).as_str();
+            if ends_line {
+                self.close_line(out);
+                *out += "\n";
+            }
+        }
+    }
+
+    fn highlight_start(&mut self, out: &mut String, highlight_name: &str) {
+        if let Some(svg) = &mut self.svg {
+            return svg.highlight_start(out, highlight_name);
+        }
+        let attributes = self.span_attributes(highlight_name);
+        if !attributes.is_empty() && (!self.per_line() || self.line_open) {
+            *out += format!("<span {}>", attributes).as_str();
+        }
+        self.open_spans.push(attributes);
+    }
+
+    fn highlight_end(&mut self, out: &mut String) {
+        if let Some(svg) = &mut self.svg {
+            return svg.highlight_end(out);
+        }
+        let attributes = self.open_spans.pop().unwrap_or_default();
+        if !attributes.is_empty() && (!self.per_line() || self.line_open) {
+            *out += "</span>";
+        }
+    }
+
+    fn block_end(&mut self, out: &mut String, block: &CodeBlock) {
+        if let Some(svg) = &mut self.svg {
+            svg.block_end(out, block);
+        } else if block.inline {
+            *out += "</code>";
+        } else if self.per_line() {
+            self.close_line(out);
+            *out += "</code></pre>\n\n";
+        } else {
+            *out += "\n</code></pre>\n\n";
+        }
+        if let Some(end) = self.figure_end.take() {
+            self.close_figure(out, &end);
+        }
+    }
+
+    fn inline_spans(&self) -> bool {
+        return true;
+    }
+}
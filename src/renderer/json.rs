@@ -0,0 +1,108 @@
+use super::{CodeBlock, Renderer};
+use serde::Serialize;
+
+/// A piece of source text, and the captures it is nested in.
+#[derive(Serialize, Debug)]
+pub struct Token {
+    pub text: String,
+    /// The byte range of the text in the markdown file.
+    pub start: usize,
+    pub end: usize,
+    /// Names of the enclosing captures, outermost first. Captures from injected languages are
+    /// nested in the captures of the block's language, e.g. `["string", "keyword"]`.
+    pub captures: Vec<String>,
+}
+
+/// The json record written for each code block.
+#[derive(Serialize, Debug)]
+pub struct BlockRecord<'a> {
+    /// The markdown file containing the block, if known.
+    pub file: Option<&'a str>,
+    /// The name of the configured language which highlighted the block, or `None` for plain
+    /// blocks.
+    pub language: Option<&'a str>,
+    /// The language as written in the info string, which may be an alias, e.g. `py`.
+    pub info_language: &'a str,
+    /// The byte range of the block, including fences.
+    pub start_byte: usize,
+    pub end_byte: usize,
+    /// The (1-indexed, inclusive) lines of the block, including fences.
+    pub start_line: usize,
+    pub end_line: usize,
+    pub tokens: &'a [Token],
+}
+
+/// Writes each code block as a single line of json, for tools which want the tokens of a
+/// block, but not html. See [`BlockRecord`] for the format.
+pub struct JsonRenderer {
+    file: Option<String>,
+    open_captures: Vec<String>,
+    /// the byte offset of the next piece of source text in the code of the block.
+    position: usize,
+    tokens: Vec<Token>,
+}
+
+impl JsonRenderer {
+    /// Creates a new [`JsonRenderer`], with `file` recorded as the source of every block.
+    pub fn new(file: Option<String>) -> JsonRenderer {
+        return JsonRenderer {
+            file,
+            open_captures: Vec::new(),
+            position: 0,
+            tokens: Vec::new(),
+        };
+    }
+}
+
+impl Renderer for JsonRenderer {
+    fn block_start(&mut self, _out: &mut String, _block: &CodeBlock) {
+        self.open_captures.clear();
+        self.tokens.clear();
+        self.position = 0;
+    }
+
+    fn source(&mut self, _out: &mut String, text: &str) {
+        self.tokens.push(Token {
+            text: text.into(),
+            start: self.position,
+            end: self.position + text.len(),
+            captures: self.open_captures.clone(),
+        });
+        self.position += text.len();
+    }
+
+    fn highlight_start(&mut self, _out: &mut String, highlight_name: &str) {
+        self.open_captures.push(highlight_name.into());
+    }
+
+    fn highlight_end(&mut self, _out: &mut String) {
+        let _ = self.open_captures.pop();
+    }
+
+    fn block_end(&mut self, out: &mut String, block: &CodeBlock) {
+        // tokens are collected with offsets into the code, which may differ from the file for
+        // blocks inside of containers. The end of a token is right after its last byte.
+        for token in self.tokens.iter_mut() {
+            let start = block.source_offset(token.start);
+            if token.end > token.start {
+                token.end = block.source_offset(token.end - 1) + 1;
+            } else {
+                token.end = start;
+            }
+            token.start = start;
+        }
+        let record = BlockRecord {
+            file: self.file.as_deref(),
+            language: block.highlighter,
+            info_language: block.language,
+            start_byte: block.range.start,
+            end_byte: block.range.end,
+            start_line: *block.lines.start(),
+            end_line: *block.lines.end(),
+            tokens: &self.tokens,
+        };
+        // records only contain strings and numbers, which always serialize.
+        *out += serde_json::to_string(&record).unwrap().as_str();
+        *out += "\n";
+    }
+}
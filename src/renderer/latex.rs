@@ -0,0 +1,178 @@
+use super::{CodeBlock, Renderer, raw_fence};
+use crate::user_config::render_options::RenderOptions;
+use crate::user_config::theme::Theme;
+use crate::user_config::treesitter_util::HIGHLIGHT_NAMES;
+
+/// Escapes text for a fancyvrb `Verbatim` environment with `commandchars=\\\{\}`.
+///
+/// Inside of it, only the command characters are special. Everything else, including
+/// `#`, `$`, `%`, `&`, `~`, `^` and `_`, is printed as is, and must not be escaped.
+pub fn latex_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped += "\\textbackslash{}",
+            '{' => escaped += "\\{",
+            '}' => escaped += "\\}",
+            _ => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+/// Returns the name of the color defined for the palette entry `name`.
+fn color_name(name: &str) -> String {
+    return format!("mordant-{name}");
+}
+
+/// Returns a color argument for `\textcolor` or `\colorbox`: a color defined in the preamble,
+/// `[HTML]{...}` for hex colors, or the color as is, for colors known to xcolor.
+fn color_argument(theme: &Theme, color: &str) -> String {
+    if theme.palette.contains_key(color) {
+        return format!("{{{}}}", color_name(color));
+    }
+    if let Some(hex) = color.strip_prefix('#') {
+        return format!("[HTML]{{{}}}", hex.to_uppercase());
+    }
+    return format!("{{{color}}}");
+}
+
+/// Returns a command defining the palette entry `name`.
+fn define_color(name: &str, color: &str) -> String {
+    match color.strip_prefix('#') {
+        Some(hex) => {
+            return format!(
+                "\\definecolor{{{}}}{{HTML}}{{{}}}\n",
+                color_name(name),
+                hex.to_uppercase()
+            );
+        }
+        None => return format!("\\colorlet{{{}}}{{{color}}}\n", color_name(name)),
+    }
+}
+
+/// Generates the preamble for documents containing blocks written with [`OutputFormat::Latex`](crate::OutputFormat::Latex).
+///
+/// Every entry of [`HIGHLIGHT_NAMES`] gets a command, styled by `theme` with the usual dotted
+/// fallback, so blocks are always typeset, even if the theme is incomplete.
+pub fn preamble(theme: &Theme) -> String {
+    let mut preamble = String::from(
+        "\\usepackage{fancyvrb}\n\\usepackage{xcolor}\n\\usepackage[normalem]{ulem}\n",
+    );
+    for (name, color) in theme.palette.iter() {
+        preamble += define_color(name, color).as_str();
+    }
+    preamble += "\\newcommand{\\mordanthl}[2]{\\csname mordant@#1\\endcsname{#2}}\n";
+    for name in HIGHLIGHT_NAMES {
+        let mut body = String::from("#1");
+        if let Some(style) = theme.style_for(name) {
+            if style.bold {
+                body = format!("\\textbf{{{body}}}");
+            }
+            if style.italic {
+                body = format!("\\textit{{{body}}}");
+            }
+            if style.underline {
+                body = format!("\\uline{{{body}}}");
+            }
+            if style.strikethrough {
+                body = format!("\\sout{{{body}}}");
+            }
+            if let Some(color) = &style.color {
+                body = format!("\\textcolor{}{{{body}}}", color_argument(theme, color));
+            }
+            if let Some(background) = &style.background {
+                body = format!("\\colorbox{}{{{body}}}", color_argument(theme, background));
+            }
+        }
+        preamble +=
+            format!("\\expandafter\\def\\csname mordant@{name}\\endcsname#1{{{body}}}\n").as_str();
+    }
+    return preamble;
+}
+
+/// Renders code blocks as raw LaTeX for pandoc, i.e. a ` ```{=latex} ` block containing a
+/// fancyvrb `Verbatim` environment. Highlights are written as `\mordanthl{name}{text}`,
+/// which is defined by [`preamble`].
+///
+/// Commands can't span lines in `Verbatim`, so highlights which are open at the end of a line
+/// are closed, and reopened at the start of the next one.
+pub struct LatexRenderer<'a> {
+    options: &'a RenderOptions,
+    open_highlights: Vec<String>,
+    /// whether the open highlights were closed at the end of the previous line.
+    line_closed: bool,
+    fence: String,
+}
+
+impl LatexRenderer<'_> {
+    pub fn new(options: &RenderOptions) -> LatexRenderer<'_> {
+        return LatexRenderer {
+            options,
+            open_highlights: Vec::new(),
+            line_closed: false,
+            fence: String::new(),
+        };
+    }
+
+    fn reopen_line(&mut self, out: &mut String) {
+        if !self.line_closed {
+            return;
+        }
+        for name in self.open_highlights.iter() {
+            *out += format!("\\mordanthl{{{name}}}{{").as_str();
+        }
+        self.line_closed = false;
+    }
+}
+
+impl Renderer for LatexRenderer<'_> {
+    fn block_start(&mut self, out: &mut String, block: &CodeBlock) {
+        self.open_highlights.clear();
+        self.line_closed = false;
+        self.fence = raw_fence(block.code);
+
+        let mut options = String::from("commandchars=\\\\\\{\\}");
+        if block.attributes.line_numbers(self.options.line_numbers) {
+            options += ",numbers=left";
+        }
+        *out += format!("{}{{=latex}}\n\\begin{{Verbatim}}[{options}]\n", self.fence).as_str();
+    }
+
+    fn source(&mut self, out: &mut String, text: &str) {
+        let mut segments = text.split('\n').peekable();
+        while let Some(segment) = segments.next() {
+            if !segment.is_empty() {
+                self.reopen_line(out);
+                *out += latex_escape(segment).as_str();
+            }
+            if segments.peek().is_some() {
+                if !self.line_closed {
+                    *out += "}".repeat(self.open_highlights.len()).as_str();
+                }
+                *out += "\n";
+                self.line_closed = true;
+            }
+        }
+    }
+
+    fn highlight_start(&mut self, out: &mut String, highlight_name: &str) {
+        self.reopen_line(out);
+        *out += format!("\\mordanthl{{{highlight_name}}}{{").as_str();
+        self.open_highlights.push(highlight_name.into());
+    }
+
+    fn highlight_end(&mut self, out: &mut String) {
+        let _ = self.open_highlights.pop();
+        if !self.line_closed {
+            *out += "}";
+        }
+    }
+
+    fn block_end(&mut self, out: &mut String, block: &CodeBlock) {
+        if !block.code.ends_with('\n') {
+            *out += "\n";
+        }
+        *out += format!("\\end{{Verbatim}}\n{}\n", self.fence).as_str();
+    }
+}
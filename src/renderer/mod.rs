@@ -0,0 +1,137 @@
+use super::info_string::InfoString;
+use super::user_config::render_options::{OutputFormat, RenderOptions};
+use super::user_config::treesitter_util::HIGHLIGHT_NAMES;
+use html::HtmlRenderer;
+use json::JsonRenderer;
+use latex::LatexRenderer;
+use std::ops::{Range, RangeInclusive};
+use svg::SvgRenderer;
+use tree_sitter_highlight::{Error, HighlightEvent};
+use typst::TypstRenderer;
+
+pub mod ansi;
+pub mod html;
+pub mod json;
+pub mod latex;
+pub mod rtf;
+pub mod svg;
+pub mod typst;
+
+/// A fenced code block, or an inline code span, which is about to be rendered.
+#[derive(Debug)]
+pub struct CodeBlock<'a> {
+    /// The language from the info string, as used to look up the highlighter, or the
+    /// `default_language` for fences without one. May be empty.
+    pub language: &'a str,
+    /// The parsed info string of the block.
+    pub attributes: &'a InfoString,
+    /// The contents of the block, without fences, and without the prefixes of the containers
+    /// the block is nested in, e.g. `> ` inside of block quotes.
+    pub code: &'a str,
+    /// The byte range of the whole block, including fences, in the markdown file.
+    pub range: Range<usize>,
+    /// The byte offset of [`CodeBlock::code`] in the markdown file.
+    pub code_offset: usize,
+    /// The byte ranges of the container prefixes which were removed from the code, in order.
+    /// See [`CodeBlock::source_offset`].
+    pub continuations: Vec<Range<usize>>,
+    /// The (1-indexed) lines of the block, including fences.
+    pub lines: RangeInclusive<usize>,
+    /// Whether there is no highlighter for the block's language, in which case the code is
+    /// passed to [`Renderer::source`] as a whole, without any highlights.
+    pub plain: bool,
+    /// The name of the highlighter for the block's language, i.e. the `name` of its entry in
+    /// `[languages]`, which may differ from an alias in the info string.
+    pub highlighter: Option<&'a str>,
+    /// Whether this is an inline code span, e.g. `` `x`{.rust} ``, rather than a fenced block.
+    /// The range of a span includes its language hint.
+    pub inline: bool,
+}
+
+impl CodeBlock<'_> {
+    /// Returns the byte offset in the markdown file of the byte at `index` in
+    /// [`CodeBlock::code`], skipping over the removed container prefixes.
+    pub fn source_offset(&self, index: usize) -> usize {
+        let mut offset = self.code_offset + index;
+        for continuation in self.continuations.iter() {
+            if continuation.start > offset {
+                break;
+            }
+            offset += continuation.len();
+        }
+        return offset;
+    }
+}
+
+/// Turns the highlight events of a code block into output text.
+///
+/// For every block, [`Renderer::block_start`] is called first, followed by the events in the
+/// order they come from the highlighter, and finally [`Renderer::block_end`]. Each hook appends
+/// to `out`, which holds the text replacing the block.
+pub trait Renderer {
+    fn block_start(&mut self, out: &mut String, block: &CodeBlock);
+    /// Called for every piece of source text, which may span several lines.
+    fn source(&mut self, out: &mut String, text: &str);
+    /// Called when a highlight starts. `highlight_name` is one of [`HIGHLIGHT_NAMES`].
+    /// Highlights may be nested, and are always ended in reverse order.
+    fn highlight_start(&mut self, out: &mut String, highlight_name: &str);
+    fn highlight_end(&mut self, out: &mut String);
+    fn block_end(&mut self, out: &mut String, block: &CodeBlock);
+    /// Whether inline code spans are rendered as well. Renderers which return `false` only
+    /// get fenced blocks.
+    fn inline_spans(&self) -> bool {
+        return false;
+    }
+}
+
+/// Renders a single block from its highlight events.
+///
+/// # Errors
+///
+/// This function will return an error if any of the highlight events is an error.
+pub fn render_block(
+    renderer: &mut dyn Renderer,
+    block: &CodeBlock,
+    events: impl Iterator<Item = Result<HighlightEvent, Error>>,
+) -> Result<String, Error> {
+    let mut out = String::new();
+    renderer.block_start(&mut out, block);
+    for event in events {
+        match event? {
+            HighlightEvent::Source { start, end } => {
+                renderer.source(&mut out, &block.code[start..end]);
+            }
+            HighlightEvent::HighlightStart(s) => {
+                renderer.highlight_start(&mut out, HIGHLIGHT_NAMES[s.0]);
+            }
+            HighlightEvent::HighlightEnd => {
+                renderer.highlight_end(&mut out);
+            }
+        }
+    }
+    renderer.block_end(&mut out, block);
+    return Ok(out);
+}
+
+/// Returns the fence for a raw block around `code`, e.g. ` ```{=latex} `.
+/// The fence is longer than any run of backticks in `code`, and at least three backticks long.
+pub fn raw_fence(code: &str) -> String {
+    let mut longest_run = 0;
+    let mut run = 0;
+    for c in code.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest_run = longest_run.max(run);
+    }
+    return "`".repeat((longest_run + 1).max(3));
+}
+
+/// Returns the [`Renderer`] for the configured [`OutputFormat`].
+pub fn from_options(options: &RenderOptions) -> Box<dyn Renderer + '_> {
+    match options.format {
+        OutputFormat::Html => return Box::new(HtmlRenderer::new(options)),
+        OutputFormat::Latex => return Box::new(LatexRenderer::new(options)),
+        OutputFormat::Typst => return Box::new(TypstRenderer::new(options)),
+        OutputFormat::Svg => return Box::new(SvgRenderer::new(options)),
+        OutputFormat::Json => return Box::new(JsonRenderer::new(None)),
+    }
+}
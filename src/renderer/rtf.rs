@@ -0,0 +1,132 @@
+use super::{CodeBlock, Renderer};
+use crate::user_config::theme::{Theme, parse_hex};
+use crate::user_config::treesitter_util::HIGHLIGHT_NAMES;
+
+/// Escapes text for RTF. `\`, `{` and `}` are escaped with a backslash, and everything outside of
+/// ASCII is written as `\uN?`, with characters outside of the BMP split into surrogate pairs.
+pub fn rtf_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\t' => escaped += "\\tab ",
+            '\n' => escaped += "\\line\n",
+            c if c.is_ascii() => escaped.push(c),
+            c => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    // \u takes a signed 16 bit number, followed by a fallback character.
+                    escaped += format!("\\u{}?", *unit as i16).as_str();
+                }
+            }
+        }
+    }
+    return escaped;
+}
+
+/// Renders a code block as an RTF document, for pasting highlighted code into office
+/// documents. Highlights are written as nested groups, e.g. `{\cf1\b return}`, which refer to
+/// a color table built from the theme.
+pub struct RtfRenderer<'a> {
+    theme: &'a Theme,
+    font: String,
+    /// Font size in points.
+    font_size: usize,
+    /// resolved colors, in the order of the color table.
+    colors: Vec<(u8, u8, u8)>,
+}
+
+impl RtfRenderer<'_> {
+    pub fn new(theme: &Theme, font: String, font_size: usize) -> RtfRenderer<'_> {
+        let mut colors: Vec<(u8, u8, u8)> = Vec::new();
+        for name in HIGHLIGHT_NAMES {
+            let Some(style) = theme.style_for(name) else {
+                continue;
+            };
+            for color in [&style.color, &style.background].into_iter().flatten() {
+                if let Some(rgb) = parse_hex(&theme.resolve_color(color))
+                    && !colors.contains(&rgb)
+                {
+                    colors.push(rgb);
+                }
+            }
+        }
+        return RtfRenderer {
+            theme,
+            font,
+            font_size,
+            colors,
+        };
+    }
+
+    /// Returns the index of `color` in the color table. Index 0 is the default color, which is
+    /// used for colors that aren't hex colors.
+    fn color_index(&self, color: &str) -> usize {
+        let Some(rgb) = parse_hex(&self.theme.resolve_color(color)) else {
+            return 0;
+        };
+        match self.colors.iter().position(|c| *c == rgb) {
+            Some(i) => return i + 1,
+            None => return 0,
+        }
+    }
+}
+
+impl Renderer for RtfRenderer<'_> {
+    fn block_start(&mut self, out: &mut String, _block: &CodeBlock) {
+        *out += "{\\rtf1\\ansi\\deff0\n";
+        *out += format!(
+            "{{\\fonttbl{{\\f0\\fmodern {};}}}}\n",
+            rtf_escape(&self.font)
+        )
+        .as_str();
+        *out += "{\\colortbl;";
+        for (r, g, b) in self.colors.iter() {
+            *out += format!("\\red{r}\\green{g}\\blue{b};").as_str();
+        }
+        *out += "}\n";
+        *out += format!("\\f0\\fs{}\n", self.font_size * 2).as_str();
+    }
+
+    fn source(&mut self, out: &mut String, text: &str) {
+        *out += rtf_escape(text).as_str();
+    }
+
+    fn highlight_start(&mut self, out: &mut String, highlight_name: &str) {
+        *out += "{";
+        let Some(style) = self.theme.style_for(highlight_name) else {
+            return;
+        };
+        if let Some(color) = &style.color {
+            *out += format!("\\cf{}", self.color_index(color)).as_str();
+        }
+        if let Some(background) = &style.background {
+            *out += format!("\\highlight{}", self.color_index(background)).as_str();
+        }
+        if style.bold {
+            *out += "\\b";
+        }
+        if style.italic {
+            *out += "\\i";
+        }
+        if style.underline {
+            *out += "\\ul";
+        }
+        if style.strikethrough {
+            *out += "\\strike";
+        }
+        // a space ends the control word, and is not part of the text.
+        *out += " ";
+    }
+
+    fn highlight_end(&mut self, out: &mut String) {
+        *out += "}";
+    }
+
+    fn block_end(&mut self, out: &mut String, _block: &CodeBlock) {
+        *out += "}\n";
+    }
+}
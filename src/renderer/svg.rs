@@ -0,0 +1,196 @@
+use super::html::{attribute_escape, html_escape};
+use super::{CodeBlock, Renderer};
+use crate::user_config::render_options::{RenderOptions, SvgOptions};
+use crate::user_config::theme::Theme;
+
+/// The width of a character of a monospace font, relative to the font size.
+const CHARACTER_WIDTH: f64 = 0.6;
+/// The height of the title bar drawn by [`SvgOptions::frame`].
+const FRAME_HEIGHT: f64 = 32.0;
+const TAB: &str = "    ";
+
+/// Formats `n` with at most two decimals, e.g. `8.4` rather than `8.399999999999999`.
+fn number(n: f64) -> String {
+    let formatted = format!("{n:.2}");
+    return formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string();
+}
+
+/// Renders code blocks as standalone `<svg>` images, with a `<tspan>` per line and highlight.
+/// Colors come from [`RenderOptions::theme_or_builtin`].
+///
+/// Like [`super::latex::LatexRenderer`], highlights which are open at the end of a line are
+/// closed, and reopened at the start of the next one, since every line is positioned on its own.
+pub struct SvgRenderer<'a> {
+    options: &'a SvgOptions,
+    theme: Theme,
+    /// attributes of the currently open highlights, e.g. `fill="#fb4934"`.
+    /// Highlights without any attributes are tracked, but not written.
+    open_tspans: Vec<String>,
+    line_open: bool,
+    /// the (0-indexed) line which is currently being written.
+    line: usize,
+    /// the y coordinate of the first line.
+    top: f64,
+}
+
+impl SvgRenderer<'_> {
+    pub fn new(options: &RenderOptions) -> SvgRenderer<'_> {
+        return SvgRenderer {
+            options: &options.svg,
+            theme: options.theme_or_builtin(),
+            open_tspans: Vec::new(),
+            line_open: false,
+            line: 0,
+            top: 0.0,
+        };
+    }
+
+    fn color(&self, color: &str) -> String {
+        return attribute_escape(&self.theme.resolve_color(color));
+    }
+
+    /// Returns the attributes for a tspan highlighting `highlight_name`.
+    fn tspan_attributes(&self, highlight_name: &str) -> String {
+        let Some(style) = self.theme.style_for(highlight_name) else {
+            return "".into();
+        };
+        let mut attributes = Vec::new();
+        if let Some(color) = &style.color {
+            attributes.push(format!("fill=\"{}\"", self.color(color)));
+        }
+        if style.bold {
+            attributes.push("font-weight=\"bold\"".into());
+        }
+        if style.italic {
+            attributes.push("font-style=\"italic\"".into());
+        }
+        let decorations: Vec<&str> = [
+            (style.underline, "underline"),
+            (style.strikethrough, "line-through"),
+        ]
+        .into_iter()
+        .filter_map(|(set, decoration)| set.then_some(decoration))
+        .collect();
+        if !decorations.is_empty() {
+            attributes.push(format!("text-decoration=\"{}\"", decorations.join(" ")));
+        }
+        return attributes.join(" ");
+    }
+
+    fn open_line(&mut self, out: &mut String) {
+        if self.line_open {
+            return;
+        }
+        let line_height = self.options.font_size * self.options.line_height;
+        *out += format!(
+            "<tspan x=\"{}\" y=\"{}\">",
+            number(self.options.padding),
+            number(self.top + self.line as f64 * line_height)
+        )
+        .as_str();
+        for attributes in self.open_tspans.iter().filter(|a| !a.is_empty()) {
+            *out += format!("<tspan {}>", attributes).as_str();
+        }
+        self.line_open = true;
+    }
+
+    fn close_line(&mut self, out: &mut String) {
+        if !self.line_open {
+            return;
+        }
+        let written_tspans = self.open_tspans.iter().filter(|a| !a.is_empty()).count();
+        *out += "</tspan>".repeat(written_tspans + 1).as_str();
+        // markdown ends html blocks at blank lines, so empty lines are not written at all.
+        *out += "\n";
+        self.line_open = false;
+    }
+}
+
+impl Renderer for SvgRenderer<'_> {
+    fn block_start(&mut self, out: &mut String, block: &CodeBlock) {
+        self.open_tspans.clear();
+        self.line_open = false;
+        self.line = 0;
+
+        let options = self.options;
+        let lines = block.code.lines().count().max(1);
+        let columns = block
+            .code
+            .lines()
+            .map(|line| line.replace('\t', TAB).chars().count())
+            .max()
+            .unwrap_or_default();
+        let line_height = options.font_size * options.line_height;
+        let frame_height = if options.frame { FRAME_HEIGHT } else { 0.0 };
+        let width = 2.0 * options.padding + columns as f64 * options.font_size * CHARACTER_WIDTH;
+        let height = 2.0 * options.padding + frame_height + lines as f64 * line_height;
+        // the baseline is placed so that capital letters (about 0.7em) are centered in the line.
+        self.top = frame_height + options.padding + line_height / 2.0 + 0.35 * options.font_size;
+
+        let (width, height) = (number(width), number(height));
+        *out += format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             viewBox=\"0 0 {width} {height}\">\n"
+        )
+        .as_str();
+        *out += format!(
+            "<rect width=\"100%\" height=\"100%\" rx=\"8\" fill=\"{}\"/>\n",
+            self.color(&options.background)
+        )
+        .as_str();
+        if options.frame {
+            for (i, color) in ["#ff5f56", "#ffbd2e", "#27c93f"].iter().enumerate() {
+                *out += format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"6\" fill=\"{color}\"/>\n",
+                    number(options.padding + 6.0 + 20.0 * i as f64),
+                    number(FRAME_HEIGHT / 2.0 + options.padding / 2.0)
+                )
+                .as_str();
+            }
+        }
+        *out += format!(
+            "<text font-family=\"{}\" font-size=\"{}\" fill=\"{}\" xml:space=\"preserve\">\n",
+            attribute_escape(&options.font_family),
+            number(options.font_size),
+            self.color(&options.foreground)
+        )
+        .as_str();
+    }
+
+    fn source(&mut self, out: &mut String, text: &str) {
+        let mut segments = text.split('\n').peekable();
+        while let Some(segment) = segments.next() {
+            if !segment.is_empty() {
+                self.open_line(out);
+                *out += html_escape(&segment.replace('\t', TAB)).as_str();
+            }
+            if segments.peek().is_some() {
+                self.close_line(out);
+                self.line += 1;
+            }
+        }
+    }
+
+    fn highlight_start(&mut self, out: &mut String, highlight_name: &str) {
+        let attributes = self.tspan_attributes(highlight_name);
+        if !attributes.is_empty() && self.line_open {
+            *out += format!("<tspan {}>", attributes).as_str();
+        }
+        self.open_tspans.push(attributes);
+    }
+
+    fn highlight_end(&mut self, out: &mut String) {
+        let attributes = self.open_tspans.pop().unwrap_or_default();
+        if !attributes.is_empty() && self.line_open {
+            *out += "</tspan>";
+        }
+    }
+
+    fn block_end(&mut self, out: &mut String, _block: &CodeBlock) {
+        self.close_line(out);
+        *out += "</text>\n</svg>\n\n";
+    }
+}
@@ -0,0 +1,118 @@
+use super::{CodeBlock, Renderer, raw_fence};
+use crate::user_config::render_options::RenderOptions;
+use crate::user_config::theme::{Theme, parse_hex};
+
+/// Escapes text for a Typst string literal.
+///
+/// Code is always written as strings rather than markup, so none of Typst's markup characters
+/// (`*`, `_`, `#`, `$`, `@`, `<`, ...) have to be escaped, and whitespace is kept as is.
+pub fn typst_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped += "\\\\",
+            '"' => escaped += "\\\"",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            _ => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+/// Returns `rgb("#...")` for hex colors. Typst doesn't know css color names, so other colors
+/// are skipped.
+fn typst_color(theme: &Theme, color: &str) -> Option<String> {
+    let color = theme.resolve_color(color);
+    let _ = parse_hex(&color)?;
+    return Some(format!("rgb(\"{color}\")"));
+}
+
+/// Renders code blocks as raw Typst for pandoc, i.e. a ` ```{=typst} ` block.
+///
+/// The block is a code block of Typst (`#{...}`), which joins the code (as strings) and the
+/// highlights (as nested `text(fill: ..., {...})` calls) into a single piece of content. It uses
+/// the same font as Typst's own `raw` elements, and colors from
+/// [`RenderOptions::theme_or_builtin`].
+pub struct TypstRenderer {
+    theme: Theme,
+    /// what closes each of the currently open highlights, e.g. `})` for `text(..., {`.
+    open_highlights: Vec<String>,
+    fence: String,
+}
+
+impl TypstRenderer {
+    pub fn new(options: &RenderOptions) -> TypstRenderer {
+        return TypstRenderer {
+            theme: options.theme_or_builtin(),
+            open_highlights: Vec::new(),
+            fence: String::new(),
+        };
+    }
+}
+
+impl Renderer for TypstRenderer {
+    fn block_start(&mut self, out: &mut String, block: &CodeBlock) {
+        self.open_highlights.clear();
+        self.fence = raw_fence(block.code);
+
+        *out += format!("{}{{=typst}}\n", self.fence).as_str();
+        *out += "#block(width: 100%, breakable: true)[#{\n";
+        *out += "set text(font: \"DejaVu Sans Mono\", size: 0.8em)\n";
+        *out += "set par(justify: false)\n";
+    }
+
+    fn source(&mut self, out: &mut String, text: &str) {
+        *out += format!("\"{}\"\n", typst_escape(text)).as_str();
+    }
+
+    fn highlight_start(&mut self, out: &mut String, highlight_name: &str) {
+        let Some(style) = self.theme.style_for(highlight_name) else {
+            // without a style, the text is simply part of the enclosing highlight.
+            self.open_highlights.push("".into());
+            return;
+        };
+        let mut arguments = Vec::new();
+        if let Some(color) = style
+            .color
+            .as_ref()
+            .and_then(|c| typst_color(&self.theme, c))
+        {
+            arguments.push(format!("fill: {color}"));
+        }
+        if style.bold {
+            arguments.push("weight: \"bold\"".into());
+        }
+        if style.italic {
+            arguments.push("style: \"italic\"".into());
+        }
+        let mut wrappers = Vec::new();
+        if style.underline {
+            wrappers.push("underline(".to_string());
+        }
+        if style.strikethrough {
+            wrappers.push("strike(".to_string());
+        }
+        if let Some(fill) = style
+            .background
+            .as_ref()
+            .and_then(|c| typst_color(&self.theme, c))
+        {
+            wrappers.push(format!("highlight(fill: {fill}, "));
+        }
+        let arguments: String = arguments.iter().map(|a| format!("{a}, ")).collect();
+        let opening = format!("{}text({arguments}{{\n", wrappers.concat());
+        let closing = format!("}}){}\n", ")".repeat(wrappers.len()));
+        *out += opening.as_str();
+        self.open_highlights.push(closing);
+    }
+
+    fn highlight_end(&mut self, out: &mut String) {
+        *out += self.open_highlights.pop().unwrap_or_default().as_str();
+    }
+
+    fn block_end(&mut self, out: &mut String, _block: &CodeBlock) {
+        *out += format!("}}]\n{}\n", self.fence).as_str();
+    }
+}
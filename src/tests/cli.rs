@@ -0,0 +1,183 @@
+//! Tests for the command line and its file handling, which are part of the binary rather than
+//! the library.
+use super::{
+    Args, IN_PLACE_MARKER, highlight_markdown, highlight_stdio, highlighted_in_place,
+    markdown_files, output_path, stdin_and_files, write_in_place,
+};
+use clap::Parser;
+use mordant::{DEFAULT_TEMPLATE, Mordant, MordantConfig};
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn input_root() {
+    let args = Args::try_parse_from(["mordant", "site/a.md"]).unwrap();
+    assert_eq!(args.input_root, ".");
+
+    let args = Args::try_parse_from(["mordant", "--strip-prefix", "site", "site/a.md"]).unwrap();
+    assert_eq!(args.input_root, "site");
+    assert_eq!(
+        output_path(
+            Path::new(&args.output_dir),
+            Path::new(&args.input_root),
+            Path::new(&args.file[0]),
+        ),
+        Some(Path::new("./mordant.out/a.md").into())
+    );
+}
+
+#[test]
+fn stdin_inputs() {
+    let files = |argv: &[&str]| {
+        let args = Args::try_parse_from(argv).unwrap();
+        let (read_stdin, files) = stdin_and_files(&args.file);
+        return (
+            read_stdin,
+            files.into_iter().map(String::from).collect::<Vec<_>>(),
+        );
+    };
+    assert_eq!(files(&["mordant"]), (true, vec![]));
+    assert_eq!(files(&["mordant", "-"]), (true, vec![]));
+    assert_eq!(files(&["mordant", "a.md"]), (false, vec!["a.md".into()]));
+    // `-` may be mixed with files and directories, which are still written to the output dir.
+    assert_eq!(
+        files(&["mordant", "a.md", "-", "docs"]),
+        (true, vec!["a.md".into(), "docs".into()])
+    );
+}
+
+#[cfg(feature = "language_all")]
+#[test]
+fn stdio() {
+    let config: MordantConfig = toml::from_str("[languages.python]\nname = \"python\"").unwrap();
+    let mordant = Mordant::new(config).unwrap();
+    let highlight = |contents: &str, name: Option<&str>| {
+        return highlight_markdown(&mordant, contents, name, DEFAULT_TEMPLATE, None);
+    };
+    let mut stdout = Vec::new();
+    highlight_stdio(
+        highlight,
+        "# Title\n```py\nNone\n```\n".as_bytes(),
+        &mut stdout,
+    )
+    .unwrap();
+    // only the document is written, without any status or file names.
+    assert_eq!(
+        String::from_utf8(stdout).unwrap(),
+        "# Title\n<pre><code><span class=\"code-constant.builtin\">None</span>\n\n</code></pre>\n\n"
+    );
+}
+
+#[test]
+fn output_paths() {
+    let out = Path::new("out");
+    let cwd = std::env::current_dir().unwrap();
+    let path = |root: &str, file: &Path| return output_path(out, Path::new(root), file);
+
+    assert_eq!(path(".", Path::new("a.md")), Some(out.join("a.md")));
+    assert_eq!(
+        path("site", Path::new("./site/posts/../a.md")),
+        Some(out.join("a.md"))
+    );
+    // absolute inputs are mapped like relative ones, instead of replacing the output dir.
+    assert_eq!(
+        path("site", &cwd.join("site/posts/a.md")),
+        Some(out.join("posts/a.md"))
+    );
+    assert_eq!(path(".", &cwd.parent().unwrap().join("a.md")), None);
+    assert_eq!(path("site", &cwd.join("a.md")), None);
+    // `..` can't escape the input root.
+    assert_eq!(path(".", Path::new("../a.md")), None);
+    assert_eq!(path("site", Path::new("site/../../a.md")), None);
+    assert_eq!(path("site", Path::new("site/../a.md")), None);
+    // the root itself isn't a file under it.
+    assert_eq!(path("site", Path::new("site")), None);
+    assert_eq!(path("site", Path::new("./site/.")), None);
+}
+
+/// Returns an empty directory for the test `name`, which is removed again by the test.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mordant-cli-{}-{}", name, std::process::id()));
+    let _ = remove_dir_all(&dir);
+    create_dir_all(&dir).unwrap();
+    return dir;
+}
+
+#[test]
+fn directory_inputs() {
+    let dir = temp_dir("directory-inputs");
+    for file in [
+        "a.md",
+        "posts/b.markdown",
+        "posts/c.mdx",
+        "notes.txt",
+        "image.png",
+        "drafts/d.md",
+        "generated/e.md",
+        "out/f.md",
+    ] {
+        create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+        write(dir.join(file), "# test\n").unwrap();
+    }
+    write(dir.join(".mordantignore"), "drafts/\n").unwrap();
+    write(dir.join(".gitignore"), "generated/\n").unwrap();
+
+    let root = dir.to_string_lossy().to_string();
+    let found = |extensions: &[&str]| {
+        let extensions: Vec<String> = extensions.iter().map(|e| return e.to_string()).collect();
+        let mut files: Vec<String> = markdown_files(&[&root], &extensions, &dir.join("out"))
+            .iter()
+            .map(|f| return f.strip_prefix(&root).unwrap().replace('\\', "/"))
+            .collect();
+        files.sort();
+        return files;
+    };
+    assert_eq!(
+        found(&["md", "markdown", "mdx"]),
+        ["/a.md", "/posts/b.markdown", "/posts/c.mdx"]
+    );
+    // a leading dot is optional.
+    assert_eq!(found(&[".md", "txt"]), ["/a.md", "/notes.txt"]);
+
+    // files are passed through as they are, even if they would be ignored.
+    let file = dir.join("drafts/d.md").to_string_lossy().to_string();
+    assert_eq!(markdown_files(&[&file], &[], &dir), vec![file.clone()]);
+    remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn in_place() {
+    let dir = temp_dir("in-place");
+    let path = dir.join("a.md");
+    write(&path, "# a\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let permissions = std::fs::Permissions::from_mode(0o640);
+        std::fs::set_permissions(&path, permissions).unwrap();
+    }
+
+    write_in_place(&path, "# b\n".into(), Some(".orig")).unwrap();
+    let contents = read_to_string(&path).unwrap();
+    assert_eq!(contents, format!("# b\n\n{}\n", IN_PLACE_MARKER));
+    assert_eq!(read_to_string(dir.join("a.md.orig")).unwrap(), "# a\n");
+    assert!(!dir.join(".a.md.mordant-tmp").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    // a second run skips the file, but quoting the marker elsewhere doesn't.
+    assert!(highlighted_in_place(&contents));
+    assert!(!highlighted_in_place(&format!(
+        "Files end with `{}`.\n\nMore text.\n",
+        IN_PLACE_MARKER
+    )));
+
+    // nothing is left behind if the original can't be replaced.
+    assert!(write_in_place(&dir.join("missing.md"), "# c\n".into(), None).is_err());
+    assert!(!dir.join(".missing.md.mordant-tmp").exists());
+    remove_dir_all(&dir).unwrap();
+}
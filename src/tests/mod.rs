@@ -1,4 +1,13 @@
-use crate::{MarkdownFile, MordantConfig};
+use crate::file_highlighter::render_code;
+use crate::renderer::ansi::{AnsiRenderer, ColorDepth};
+use crate::renderer::json::JsonRenderer;
+use crate::renderer::rtf::RtfRenderer;
+use crate::renderer::{CodeBlock, Renderer, latex, raw_fence};
+use crate::user_config::class_scheme::ClassScheme;
+use crate::{
+    MarkdownFile, Mordant, MordantConfig, MordantError, MordantWarning, RenderOptions, Theme, css,
+    document,
+};
 use prettydiff::text::{ContextConfig, diff_lines};
 
 #[macro_export]
@@ -39,11 +48,12 @@ fn pretty_assert_eq(v1: &str, v2: &str) {
 fn format_doc(config: &str, unformatted: &str, formatted: &str) {
     let default_config: MordantConfig = toml::from_str(config).unwrap();
 
-    let highlighters = default_config.get_highlight_configurations().unwrap();
+    let render_options = default_config.render_options();
+    let (highlighters, _) = default_config.get_highlight_configurations().unwrap();
 
-    let mut file = MarkdownFile::new(unformatted.into(), &highlighters);
+    let mut file = MarkdownFile::new(unformatted.into(), &highlighters, &render_options);
 
-    file.format();
+    file.format().unwrap();
 
     pretty_assert_eq(&file.contents(), formatted);
 }
@@ -55,5 +65,278 @@ doc_test!(
     "json",
     "multiple-langs",
     "injected",
+    "line-numbers",
+    "highlighted-lines",
+    "line-classes-prefix",
+    "inline-styles",
+    "inline-styles-builtin",
+    "hierarchical-classes",
+    "highlightjs-classes",
+    "pygments-classes",
+    "template-classes",
+    "latex",
+    "svg",
+    "typst",
+    "aliases",
+    "plain-fallback",
+    "default-language",
+    "inline-code",
+    "pandoc-attributes",
+    "figures",
+    "figure-template",
+    "nested-lists",
+    "nested-blockquotes",
+    "eof-fence",
+    "eof-fence-blockquote",
     format_doc
 );
+
+#[cfg(test)]
+#[test]
+fn theme_stylesheet() {
+    let theme: Theme = toml::from_str(include_str!("./css/theme.toml")).unwrap();
+    pretty_assert_eq(
+        &css::stylesheet(&theme, &ClassScheme::Dotted, "code-"),
+        include_str!("./css/output.css"),
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn empty_theme_stylesheet() {
+    // without a [theme], stylesheets are generated from example-theme.toml.
+    let options = RenderOptions::default();
+    let stylesheet = css::stylesheet(&options.theme_or_builtin(), &ClassScheme::Dotted, "code-");
+    pretty_assert_eq(
+        &stylesheet,
+        &css::stylesheet(&Theme::builtin(), &ClassScheme::Dotted, "code-"),
+    );
+    assert!(stylesheet.contains(".code-keyword {"));
+}
+
+#[cfg(test)]
+#[test]
+fn missing_styles() {
+    assert!(Theme::builtin().missing_styles().is_empty());
+    let theme: Theme = toml::from_str("keyword = \"red\"").unwrap();
+    let missing = theme.missing_styles();
+    assert!(missing.contains(&"variable"));
+    assert!(!missing.contains(&"keyword.return"));
+}
+
+#[cfg(test)]
+#[test]
+fn raw_fences() {
+    assert_eq!(raw_fence("x = 1"), "```");
+    assert_eq!(raw_fence("`a` and ```b```"), "````");
+    assert_eq!(raw_fence("`````"), "``````");
+}
+
+#[cfg(test)]
+#[test]
+fn latex_preamble() {
+    let config: MordantConfig = toml::from_str(include_str!("./latex/mordant.toml")).unwrap();
+    pretty_assert_eq(
+        &latex::preamble(&config.render_options().theme),
+        include_str!("./latex/preamble.tex"),
+    );
+}
+
+/// Writes highlights as `[name]...[/]`, to check that custom renderers see every event.
+#[cfg(test)]
+struct BracketRenderer;
+
+#[cfg(test)]
+impl Renderer for BracketRenderer {
+    fn block_start(&mut self, out: &mut String, block: &CodeBlock) {
+        *out += format!("<{}>\n", block.language).as_str();
+    }
+    fn source(&mut self, out: &mut String, text: &str) {
+        *out += text;
+    }
+    fn highlight_start(&mut self, out: &mut String, highlight_name: &str) {
+        *out += format!("[{highlight_name}]").as_str();
+    }
+    fn highlight_end(&mut self, out: &mut String) {
+        *out += "[/]";
+    }
+    fn block_end(&mut self, out: &mut String, block: &CodeBlock) {
+        *out += format!("</{}>\n", block.language).as_str();
+    }
+}
+
+#[cfg(feature = "language_all")]
+#[test]
+fn custom_renderer() {
+    let config: MordantConfig = toml::from_str("[languages.python]\nname = \"python\"").unwrap();
+    let render_options = config.render_options();
+    let (highlighters, _) = config.get_highlight_configurations().unwrap();
+    let mut file = MarkdownFile::new(
+        "text\n```python\nx = 1\n```\n".into(),
+        &highlighters,
+        &render_options,
+    )
+    .with_renderer(Box::new(BracketRenderer));
+    file.format().unwrap();
+    pretty_assert_eq(
+        &file.contents(),
+        "text\n<python>\n[variable]x[/] [operator]=[/] [number]1[/]\n</python>\n",
+    );
+}
+
+#[cfg(feature = "language_all")]
+#[test]
+fn ansi_renderer() {
+    let config: MordantConfig = toml::from_str("[languages.python]\nname = \"python\"").unwrap();
+    let render_options = config.render_options();
+    let (highlighters, _) = config.get_highlight_configurations().unwrap();
+    let theme: Theme =
+        toml::from_str("keyword = { color = \"red\", bold = true }\n[palette]\nred = \"#fb4934\"")
+            .unwrap();
+    let markdown = "```python\nreturn x\n```\n";
+
+    for (depth, expected) in [
+        (
+            ColorDepth::TrueColor,
+            "\x1b[1;38;2;251;73;52mreturn\x1b[0m x\n",
+        ),
+        (ColorDepth::Ansi256, "\x1b[1;38;5;203mreturn\x1b[0m x\n"),
+    ] {
+        let mut file = MarkdownFile::new(markdown.into(), &highlighters, &render_options)
+            .with_renderer(Box::new(AnsiRenderer::new(theme.clone(), depth)));
+        file.format().unwrap();
+        pretty_assert_eq(
+            &file.contents(),
+            &format!("\x1b[2m```python\x1b[0m\n{expected}\x1b[0m\x1b[2m```\x1b[0m\n"),
+        );
+    }
+}
+
+#[cfg(feature = "language_all")]
+#[test]
+fn rtf_snippet() {
+    let config: MordantConfig = toml::from_str("[languages.python]\nname = \"python\"").unwrap();
+    let (highlighters, _) = config.get_highlight_configurations().unwrap();
+    let theme: Theme = toml::from_str(
+        "keyword = { color = \"red\", bold = true }\nstring = \"#b8bb26\"\n[palette]\nred = \"#fb4934\"",
+    )
+    .unwrap();
+    let mut renderer = RtfRenderer::new(&theme, "Menlo".into(), 11);
+    pretty_assert_eq(
+        &render_code(
+            &mut renderer,
+            &highlighters,
+            "python",
+            "return \"{\\}\"\n\"é😀\"",
+        )
+        .unwrap(),
+        "{\\rtf1\\ansi\\deff0\n{\\fonttbl{\\f0\\fmodern Menlo;}}\n\
+         {\\colortbl;\\red184\\green187\\blue38;\\red251\\green73\\blue52;}\n\\f0\\fs22\n\
+         {\\cf2\\b return} {\\cf1 \"\\{\\\\\\}\"}\\line\n\
+         {\\cf1 \"\\u233?\\u-10179?\\u-8704?\"}}\n",
+    );
+}
+
+#[cfg(feature = "language_all")]
+#[test]
+fn json_records() {
+    let config: MordantConfig =
+        toml::from_str(include_str!("./json-records/mordant.toml")).unwrap();
+    let render_options = config.render_options();
+    let (highlighters, _) = config.get_highlight_configurations().unwrap();
+    let mut file = MarkdownFile::new(
+        include_str!("./json-records/input.md").into(),
+        &highlighters,
+        &render_options,
+    )
+    .with_renderer(Box::new(JsonRenderer::new(Some("input.md".into()))));
+    let records: String = file
+        .get_edits()
+        .unwrap()
+        .into_iter()
+        .map(|e| e.formatted)
+        .collect();
+    pretty_assert_eq(&records, include_str!("./json-records/output.jsonl"));
+}
+
+#[cfg(feature = "language_all")]
+#[test]
+fn standalone_document() {
+    let config: MordantConfig = toml::from_str(include_str!("./standalone/mordant.toml")).unwrap();
+    let render_options = config.render_options();
+    let (highlighters, _) = config.get_highlight_configurations().unwrap();
+    let mut file = MarkdownFile::new(
+        include_str!("./standalone/input.md").into(),
+        &highlighters,
+        &render_options,
+    );
+    pretty_assert_eq(
+        &file
+            .render_document()
+            .unwrap()
+            .fill_template(document::DEFAULT_TEMPLATE, None),
+        include_str!("./standalone/output.html"),
+    );
+}
+
+#[cfg(feature = "language_all")]
+#[test]
+fn highlighter_aliases() {
+    // `py` is both the key, and one of the builtin aliases of python.
+    let config: MordantConfig =
+        toml::from_str("[languages.py]\nname = \"python\"\n[languages.js]\nname = \"javascript\"")
+            .unwrap();
+    let (mut highlighters, _) = config.get_highlight_configurations().unwrap();
+    assert!(highlighters.add_alias("py", "py"));
+    assert!(highlighters.add_alias("Python", "py"));
+    assert!(!highlighters.add_alias("py", "js"));
+    assert_eq!(highlighters.get("PY").unwrap().language_name, "python");
+}
+
+#[cfg(feature = "language_all")]
+#[test]
+fn warnings() {
+    let config: MordantConfig = toml::from_str(
+        "[languages.python]\nname = \"python\"\n[languages.js]\nname = \"javascript\"\naliases = [\"python\"]",
+    )
+    .unwrap();
+    let mordant = Mordant::new(config).unwrap();
+    assert_eq!(
+        mordant.warnings(),
+        [MordantWarning::AliasTaken {
+            alias: "python".into(),
+            name: "js".into()
+        }]
+    );
+    let mut file = mordant.markdown_file("# Title\n\n```{python hl_lines=\"1 a-b\"}\nNone\n```\n");
+    file.format().unwrap();
+    assert_eq!(
+        file.warnings(),
+        [MordantWarning::InvalidLineRange {
+            range: "a-b".into(),
+            line: 3
+        }]
+    );
+}
+
+#[cfg(feature = "language_all")]
+#[test]
+fn library_api() {
+    let config: MordantConfig = toml::from_str("[languages.python]\nname = \"python\"").unwrap();
+    let mordant = Mordant::new(config).unwrap();
+    pretty_assert_eq(
+        &mordant
+            .highlight_markdown("# Title\n```py\nNone\n```\n")
+            .unwrap(),
+        "# Title\n<pre><code><span class=\"code-constant.builtin\">None</span>\n\n</code></pre>\n\n",
+    );
+    pretty_assert_eq(
+        &mordant.highlight_code("Python", "None").unwrap(),
+        "<pre><code><span class=\"code-constant.builtin\">None</span>\n</code></pre>\n\n",
+    );
+    assert!(matches!(
+        mordant.highlight_code("sh", "ls"),
+        Err(MordantError::UnknownLanguage(language)) if language == "sh"
+    ));
+    assert_eq!(mordant.render_document("# Title\n").unwrap().title, "Title");
+}
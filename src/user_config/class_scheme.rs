@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+
+/// How highlight names are turned into class names.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClassScheme {
+    /// One class with the full capture name, e.g. `code-keyword.return`.
+    #[default]
+    Dotted,
+    /// One class per level of the capture name, joined with dashes, e.g.
+    /// `code-keyword code-keyword-return`. Selectors don't need escaping, and styles for
+    /// `keyword` cascade to `keyword.return`.
+    Hierarchical,
+    /// Classes styled by highlight.js themes, e.g. `hljs-title function_`.
+    HighlightJs,
+    /// Classes styled by Prism themes, e.g. `token keyword`.
+    Prism,
+    /// Short token classes styled by Pygments themes, e.g. `k`, `s2`, `nf`.
+    Pygments,
+    /// A custom template, where `{name}` is replaced with the capture name,
+    /// e.g. `class_scheme = { template = "tok tok-{name}" }`.
+    Template(String),
+}
+
+/// Maps capture names to highlight.js scopes.
+/// Names without an entry use the entry of their dotted parent.
+const HIGHLIGHTJS_CLASSES: &[(&str, &str)] = &[
+    ("variable", "hljs-variable"),
+    ("variable.builtin", "hljs-variable language_"),
+    ("variable.parameter", "hljs-params"),
+    ("variable.member", "hljs-property"),
+    ("constant", "hljs-variable constant_"),
+    ("constant.builtin", "hljs-literal"),
+    ("constant.macro", "hljs-meta"),
+    ("module", "hljs-title"),
+    ("label", "hljs-symbol"),
+    ("string", "hljs-string"),
+    ("string.regexp", "hljs-regexp"),
+    ("string.escape", "hljs-char escape_"),
+    ("string.special", "hljs-subst"),
+    ("string.special.symbol", "hljs-symbol"),
+    ("string.special.path", "hljs-link"),
+    ("string.special.url", "hljs-link"),
+    ("character", "hljs-string"),
+    ("character.special", "hljs-char escape_"),
+    ("boolean", "hljs-literal"),
+    ("number", "hljs-number"),
+    ("type", "hljs-type"),
+    ("type.builtin", "hljs-built_in"),
+    ("type.definition", "hljs-title class_"),
+    ("attribute", "hljs-meta"),
+    ("property", "hljs-property"),
+    ("function", "hljs-title function_"),
+    ("function.builtin", "hljs-built_in"),
+    ("function.call", "hljs-title function_ invoke__"),
+    ("function.macro", "hljs-meta"),
+    ("function.method.call", "hljs-title function_ invoke__"),
+    ("constructor", "hljs-title class_"),
+    ("operator", "hljs-operator"),
+    ("keyword", "hljs-keyword"),
+    ("keyword.directive", "hljs-meta"),
+    ("punctuation", "hljs-punctuation"),
+    ("comment", "hljs-comment"),
+    ("comment.todo", "hljs-doctag"),
+    ("comment.note", "hljs-doctag"),
+    ("markup.strong", "hljs-strong"),
+    ("markup.italic", "hljs-emphasis"),
+    ("markup.heading", "hljs-section"),
+    ("markup.quote", "hljs-quote"),
+    ("markup.math", "hljs-formula"),
+    ("markup.link", "hljs-link"),
+    ("markup.link.label", "hljs-string"),
+    ("markup.raw", "hljs-code"),
+    ("markup.list", "hljs-bullet"),
+    ("diff.plus", "hljs-addition"),
+    ("diff.minus", "hljs-deletion"),
+    ("diff.delta", "hljs-meta"),
+    ("tag", "hljs-name"),
+    ("tag.attribute", "hljs-attr"),
+    ("tag.delimiter", "hljs-tag"),
+];
+
+/// Maps capture names to Prism tokens.
+/// Names without an entry use the entry of their dotted parent.
+const PRISM_CLASSES: &[(&str, &str)] = &[
+    ("variable", "token variable"),
+    ("variable.builtin", "token builtin"),
+    ("variable.member", "token property"),
+    ("constant", "token constant"),
+    ("module", "token namespace"),
+    ("label", "token symbol"),
+    ("string", "token string"),
+    ("string.regexp", "token regex"),
+    ("string.escape", "token entity"),
+    ("string.special.symbol", "token symbol"),
+    ("string.special.path", "token url"),
+    ("string.special.url", "token url"),
+    ("character", "token char"),
+    ("boolean", "token boolean"),
+    ("number", "token number"),
+    ("type", "token class-name"),
+    ("type.builtin", "token builtin"),
+    ("attribute", "token decorator"),
+    ("property", "token property"),
+    ("function", "token function"),
+    ("function.builtin", "token builtin"),
+    ("constructor", "token class-name"),
+    ("operator", "token operator"),
+    ("keyword", "token keyword"),
+    ("keyword.directive", "token directive"),
+    ("punctuation", "token punctuation"),
+    ("comment", "token comment"),
+    ("markup.strong", "token bold"),
+    ("markup.italic", "token italic"),
+    ("markup.strikethrough", "token strike"),
+    ("markup.heading", "token title important"),
+    ("markup.quote", "token blockquote punctuation"),
+    ("markup.link", "token url"),
+    ("markup.raw", "token code"),
+    ("markup.list", "token list punctuation"),
+    ("diff.plus", "token inserted"),
+    ("diff.minus", "token deleted"),
+    ("diff.delta", "token coord"),
+    ("tag", "token tag"),
+    ("tag.attribute", "token attr-name"),
+    ("tag.delimiter", "token punctuation"),
+];
+
+/// Maps capture names to Pygments' short token classes.
+/// Names without an entry use the entry of their dotted parent.
+const PYGMENTS_CLASSES: &[(&str, &str)] = &[
+    ("variable", "n"),
+    ("variable.builtin", "bp"),
+    ("variable.member", "py"),
+    ("constant", "no"),
+    ("constant.builtin", "kc"),
+    ("constant.macro", "cp"),
+    ("module", "nn"),
+    ("label", "nl"),
+    ("string", "s"),
+    ("string.documentation", "sd"),
+    ("string.regexp", "sr"),
+    ("string.escape", "se"),
+    ("string.special", "sx"),
+    ("string.special.symbol", "ss"),
+    ("character", "sc"),
+    ("character.special", "se"),
+    ("boolean", "kc"),
+    ("number", "m"),
+    ("number.float", "mf"),
+    ("type", "nc"),
+    ("type.builtin", "kt"),
+    ("attribute", "nd"),
+    ("property", "py"),
+    ("function", "nf"),
+    ("function.builtin", "nb"),
+    ("function.call", "n"),
+    ("function.method.call", "n"),
+    ("constructor", "nc"),
+    ("operator", "o"),
+    ("keyword", "k"),
+    ("keyword.function", "kd"),
+    ("keyword.operator", "ow"),
+    ("keyword.import", "kn"),
+    ("keyword.type", "kd"),
+    ("keyword.modifier", "kd"),
+    ("keyword.directive", "cp"),
+    ("punctuation", "p"),
+    ("comment", "c"),
+    ("comment.documentation", "cs"),
+    ("markup.strong", "gs"),
+    ("markup.italic", "ge"),
+    ("markup.heading", "gh"),
+    ("markup.raw", "sb"),
+    ("diff.plus", "gi"),
+    ("diff.minus", "gd"),
+    ("diff.delta", "gu"),
+    ("tag", "nt"),
+    ("tag.attribute", "na"),
+    ("tag.delimiter", "p"),
+];
+
+/// Looks `highlight_name` up in `table`, falling back to its dotted parents.
+fn lookup(table: &[(&str, &'static str)], highlight_name: &str) -> Option<&'static str> {
+    let mut name = highlight_name;
+    loop {
+        if let Some((_, classes)) = table.iter().find(|(n, _)| *n == name) {
+            return Some(classes);
+        }
+        match name.rsplit_once('.') {
+            Some((parent, _)) => name = parent,
+            None => return None,
+        }
+    }
+}
+
+impl ClassScheme {
+    /// Returns the space separated classes for `highlight_name`, which may be empty if a preset
+    /// has no equivalent. `prefix` replaces `code-` for the dotted and hierarchical schemes.
+    pub fn classes(&self, prefix: &str, highlight_name: &str) -> String {
+        match self {
+            Self::Dotted => return format!("{prefix}{highlight_name}"),
+            Self::Hierarchical => {
+                let mut classes: Vec<String> = Vec::new();
+                let mut class = String::from(prefix);
+                for (i, part) in highlight_name.split('.').enumerate() {
+                    if i > 0 {
+                        class += "-";
+                    }
+                    class += part;
+                    classes.push(class.clone());
+                }
+                return classes.join(" ");
+            }
+            Self::HighlightJs => {
+                return lookup(HIGHLIGHTJS_CLASSES, highlight_name)
+                    .unwrap_or_default()
+                    .into();
+            }
+            Self::Prism => {
+                return lookup(PRISM_CLASSES, highlight_name)
+                    .unwrap_or_default()
+                    .into();
+            }
+            Self::Pygments => {
+                return lookup(PYGMENTS_CLASSES, highlight_name)
+                    .unwrap_or_default()
+                    .into();
+            }
+            Self::Template(template) => return template.replace("{name}", highlight_name),
+        }
+    }
+
+    /// Returns the classes for the `<pre>` and `<code>` elements of a block in `language`,
+    /// which the themes of the respective preset expect.
+    pub fn block_classes(&self, language: &str) -> (Option<String>, Option<String>) {
+        match self {
+            Self::HighlightJs => return (None, Some(format!("hljs language-{language}"))),
+            Self::Prism => {
+                let class = format!("language-{language}");
+                return (Some(class.clone()), Some(class));
+            }
+            Self::Pygments => return (Some("highlight".into()), None),
+            _ => return (None, None),
+        }
+    }
+
+    /// Returns a css selector matching every element highlighted as `highlight_name`,
+    /// or `None` if this scheme writes no classes for it.
+    pub fn selector(&self, prefix: &str, highlight_name: &str) -> Option<String> {
+        let classes = self.classes(prefix, highlight_name);
+        let selector: String = match self {
+            // the last class is the most specific one.
+            Self::Hierarchical => classes
+                .split_whitespace()
+                .last()
+                .map(|c| format!(".{c}"))
+                .unwrap_or_default(),
+            _ => classes
+                .split_whitespace()
+                .map(|c| format!(".{}", c.replace(".", "\\.")))
+                .collect(),
+        };
+        if selector.is_empty() {
+            return None;
+        }
+        if *self == Self::Pygments {
+            return Some(format!(".highlight {selector}"));
+        }
+        return Some(selector);
+    }
+}
@@ -1,5 +1,3 @@
-use libloading;
-use shellexpand;
 use std::{env::VarError, fmt, io, path::PathBuf, result::Result};
 pub type MordantConfigResult<T> = Result<T, MordantConfigError>;
 impl<T> From<MordantConfigError> for MordantConfigResult<T> {
@@ -75,3 +73,15 @@ impl fmt::Display for MordantConfigError {
         }
     }
 }
+
+impl std::error::Error for MordantConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::IO(err) => return Some(err),
+            Self::LanguageSource { error, .. } => return Some(error),
+            Self::TreeSitterQuery(err) => return Some(err),
+            Self::ShellExpandError(err) => return Some(err),
+            Self::InvalidPath(_) | Self::NotSupported(_) => return None,
+        }
+    }
+}
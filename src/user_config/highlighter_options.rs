@@ -1,5 +1,4 @@
 use serde::{Deserialize, Serialize};
-use shellexpand;
 use std::{fs::read_to_string, path::PathBuf};
 use tree_sitter::Language;
 use tree_sitter_highlight::HighlightConfiguration;
@@ -7,8 +6,8 @@ use tree_sitter_highlight::HighlightConfiguration;
 use super::{
     error::{MordantConfigError, MordantConfigResult},
     treesitter_util::{
-        HIGHLIGHT_NAMES, get_builtin_highlights, get_builtin_language, get_builtin_locals,
-        get_language_from_source_file, strip_nonstandard_predicates,
+        HIGHLIGHT_NAMES, get_builtin_aliases, get_builtin_highlights, get_builtin_language,
+        get_builtin_locals, get_language_from_source_file, strip_nonstandard_predicates,
     },
 };
 
@@ -63,6 +62,10 @@ fn _false() -> bool {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct MordantHighlighterConfig {
     pub name: String,
+    /// Other names for the language in info strings and injections, e.g. `["py", "python3"]`.
+    /// Builtin languages also get the aliases from [`get_builtin_aliases`].
+    #[serde(default)]
+    pub aliases: Vec<String>,
     #[serde(default)]
     pub language: LanguageSrc,
     #[serde(default)]
@@ -171,6 +174,17 @@ impl MordantHighlighterConfig {
         }
     }
 
+    /// Returns the configured aliases, followed by the builtin aliases for this language.
+    pub fn aliases(&self) -> Vec<String> {
+        let mut aliases = self.aliases.clone();
+        aliases.extend(
+            get_builtin_aliases(&self.name)
+                .iter()
+                .map(|a| a.to_string()),
+        );
+        return aliases;
+    }
+
     pub fn set_base_dir(mut self, base_dir: &PathBuf) -> Self {
         match self.language {
             LanguageSrc::FromSource { path, symbol_name } => {
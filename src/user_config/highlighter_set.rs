@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+use tree_sitter_highlight::HighlightConfiguration;
+
+/// The configured highlighters, keyed by their name in `[languages]`, and looked up by name or
+/// alias. Lookups are case-insensitive, so ` ```Python ` and ` ```py ` both find `python`.
+#[derive(Default)]
+pub struct HighlighterSet {
+    highlighters: BTreeMap<String, HighlightConfiguration>,
+    /// lowercase names and aliases, pointing to a key of `highlighters`.
+    aliases: BTreeMap<String, String>,
+}
+
+impl HighlighterSet {
+    /// Adds `config` under `name`. A highlighter's name always takes precedence over aliases of
+    /// other highlighters.
+    pub fn insert(&mut self, name: String, config: HighlightConfiguration) {
+        let _ = self.aliases.insert(name.to_lowercase(), name.clone());
+        let _ = self.highlighters.insert(name, config);
+    }
+
+    /// Adds `alias` for the highlighter `name`, unless it is already taken.
+    /// Returns whether the alias points to `name`, i.e. `false` if it is taken by another
+    /// highlighter.
+    pub fn add_alias(&mut self, alias: &str, name: &str) -> bool {
+        if let Some(existing) = self.aliases.get(&alias.to_lowercase()) {
+            return existing == name;
+        }
+        let _ = self.aliases.insert(alias.to_lowercase(), name.into());
+        return true;
+    }
+
+    /// Returns the highlighter for `name`, which may be the name of a highlighter or an alias,
+    /// in any case.
+    pub fn get(&self, name: &str) -> Option<&HighlightConfiguration> {
+        if let Some(config) = self.highlighters.get(name) {
+            return Some(config);
+        }
+        let name = self.aliases.get(&name.to_lowercase())?;
+        return self.highlighters.get(name);
+    }
+}
@@ -1,11 +1,19 @@
+use crate::error::{MordantResult, MordantWarning};
 use error::MordantConfigResult;
 use highlighter_options::MordantHighlighterConfig;
+use highlighter_set::HighlighterSet;
+use render_options::RenderOptions;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::path::PathBuf;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
 use tree_sitter_highlight::HighlightConfiguration;
+pub(crate) mod class_scheme;
 pub(crate) mod error;
 pub(crate) mod highlighter_options;
+pub(crate) mod highlighter_set;
+pub(crate) mod render_options;
+pub(crate) mod theme;
 pub(crate) mod treesitter_util;
 
 fn default_nvim_treesitter() -> PathBuf {
@@ -21,19 +29,67 @@ pub struct MordantConfig {
     #[serde(default = "BTreeMap::default")]
     languages: BTreeMap<String, MordantHighlighterConfig>,
     base_dir: Option<PathBuf>,
+    #[serde(flatten)]
+    render: RenderOptions,
 }
 
 impl MordantConfig {
+    /// Reads a [`MordantConfig`] from the toml file at `path`. Relative paths in the config are
+    /// resolved from the directory containing it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the file can't be read, or isn't a valid config.
+    pub fn from_file(path: impl AsRef<Path>) -> MordantResult<MordantConfig> {
+        let path = path.as_ref();
+        let config: MordantConfig = toml::from_str(read_to_string(path)?.as_str())?;
+        let base_dir = path
+            .canonicalize()?
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        return Ok(config.with_base_dir(base_dir));
+    }
+
+    /// Builds the highlighters for every configured language.
+    /// Languages are found by their key in `[languages]`, or any of their aliases.
+    /// Aliases which are already taken are skipped, and returned next to the highlighters.
     pub fn get_highlight_configurations(
         self,
-    ) -> MordantConfigResult<BTreeMap<String, HighlightConfiguration>> {
-        let mut configs: BTreeMap<String, HighlightConfiguration> = BTreeMap::default();
+    ) -> MordantConfigResult<(HighlighterSet, Vec<MordantWarning>)> {
+        let mut configs = HighlighterSet::default();
         let base_dir = &self.base_dir.unwrap_or_default();
+        let mut aliases: Vec<(String, Vec<String>)> = Vec::new();
         for (name, mut lang) in self.languages.into_iter() {
             lang = lang.set_base_dir(&base_dir);
-            let _ = configs.insert(name, lang.try_into()?);
+            aliases.push((name.clone(), lang.aliases()));
+            let config: HighlightConfiguration = lang.try_into()?;
+            configs.insert(name, config);
+        }
+        let mut warnings = Vec::new();
+        // aliases are added once every name is known, so that names always take precedence.
+        for (name, aliases) in aliases {
+            for alias in aliases {
+                if !configs.add_alias(&alias, &name) {
+                    warnings.push(MordantWarning::AliasTaken {
+                        alias,
+                        name: name.clone(),
+                    });
+                }
+            }
+        }
+        return Ok((configs, warnings));
+    }
+
+    /// Returns the [`RenderOptions`] of this [`MordantConfig`].
+    /// Call this before [`MordantConfig::get_highlight_configurations`], which consumes the config.
+    /// Relative template paths are resolved from the base dir.
+    pub fn render_options(&self) -> RenderOptions {
+        let mut options = self.render.clone();
+        if let (Some(base_dir), Some(template)) = (&self.base_dir, &options.document.template) {
+            options.document.template = Some(base_dir.join(template));
         }
-        return Ok(configs);
+        return options;
     }
 
     pub fn with_base_dir(mut self, path: PathBuf) -> Self {
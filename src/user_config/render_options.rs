@@ -0,0 +1,181 @@
+use super::class_scheme::ClassScheme;
+use super::theme::Theme;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// What code blocks are turned into.
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// `<pre><code>` html fragments.
+    #[default]
+    Html,
+    /// Raw LaTeX blocks for pandoc, using fancyvrb.
+    Latex,
+    /// Raw Typst blocks for pandoc.
+    Typst,
+    /// Standalone `<svg>` images, e.g. for social cards and slides.
+    Svg,
+    /// One json record per block, with the tokens of the block, instead of markdown.
+    Json,
+}
+
+/// How the language of an inline code span is given, if inline code is highlighted at all.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InlineCodeSyntax {
+    /// Inline code is never highlighted.
+    #[default]
+    Off,
+    /// A pandoc style attribute after the span, e.g. `` `SELECT *`{.sql} ``.
+    Attribute,
+    /// The language in braces at the start of the span, e.g. `` `{rust} Vec<T>` ``.
+    Prefix,
+    /// Either of the above.
+    Both,
+}
+
+impl InlineCodeSyntax {
+    pub fn attribute(&self) -> bool {
+        return matches!(self, Self::Attribute | Self::Both);
+    }
+
+    pub fn prefix(&self) -> bool {
+        return matches!(self, Self::Prefix | Self::Both);
+    }
+}
+
+/// Options for writing whole documents as html pages, in the `[document]` table.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct DocumentOptions {
+    /// Write complete html pages, instead of markdown with html code blocks.
+    pub standalone: bool,
+    /// Template with `{{title}}`, `{{stylesheet}}` and `{{content}}` slots.
+    /// Relative paths are resolved from the directory of the config file.
+    pub template: Option<PathBuf>,
+    /// Fill the `{{stylesheet}}` slot with a stylesheet generated from [`RenderOptions::theme`].
+    pub embed_stylesheet: bool,
+}
+
+/// Options for blocks with a `title` in their info string, in the `[figure]` table.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct FigureOptions {
+    /// Add a badge with the name of the language to every figure, and wrap blocks without a
+    /// title in figures as well.
+    pub badge: bool,
+    /// Template with `{{title}}`, `{{badge}}`, `{{language}}` and `{{code}}` slots.
+    pub template: String,
+    /// Template for the badge, with a `{{language}}` slot.
+    pub badge_template: String,
+}
+
+impl Default for FigureOptions {
+    fn default() -> Self {
+        return FigureOptions {
+            badge: false,
+            template: "<figure class=\"code-figure\">\n\
+                <figcaption>{{title}}{{badge}}</figcaption>\n\
+                {{code}}\n\
+                </figure>"
+                .into(),
+            badge_template: "<span class=\"code-badge\">{{language}}</span>".into(),
+        };
+    }
+}
+
+/// Options for blocks written as svg images, in the `[svg]` table.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SvgOptions {
+    pub font_family: String,
+    /// Font size in pixels.
+    pub font_size: f64,
+    /// Line height, relative to the font size.
+    pub line_height: f64,
+    /// Padding around the code in pixels.
+    pub padding: f64,
+    /// Colors are css colors, or names from the palette of [`RenderOptions::theme`].
+    pub background: String,
+    /// The color of text without a style.
+    pub foreground: String,
+    /// Draw a title bar with window buttons above the code.
+    pub frame: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        return SvgOptions {
+            font_family: "ui-monospace, SFMono-Regular, Menlo, Consolas, monospace".into(),
+            font_size: 14.0,
+            line_height: 1.5,
+            padding: 16.0,
+            background: "#282828".into(),
+            foreground: "#ebdbb2".into(),
+            frame: false,
+        };
+    }
+}
+
+/// Options controlling how highlighted code blocks are written out.
+/// These live at the top level of `mordant.toml`, next to `[languages]`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RenderOptions {
+    /// What code blocks are turned into, e.g. `format = "latex"`.
+    pub format: OutputFormat,
+    /// Wrap every line of every block in its own element, with a line number gutter.
+    /// Individual blocks can override this with `linenos` or `nolinenos` in their info string.
+    pub line_numbers: bool,
+    /// How capture names are turned into classes, e.g. `class_scheme = "hierarchical"`.
+    pub class_scheme: ClassScheme,
+    /// Prefix for the classes of the `dotted` and `hierarchical` class schemes, and for the
+    /// `line`, `line-numbers`, `line-highlighted` and `plain` classes of every scheme.
+    pub class_prefix: String,
+    /// Write `style` attributes from [`RenderOptions::theme`] instead of `code-` classes,
+    /// for targets that strip stylesheets.
+    pub inline_styles: bool,
+    /// Write fenced blocks with an unknown language as plain text, rather than leaving them as
+    /// markdown. Inline spans are only highlighted for configured languages.
+    pub plain_fallback: bool,
+    /// The language for fences without an info string.
+    pub default_language: Option<String>,
+    /// Highlight inline code spans with a language hint, e.g. `inline_code = "attribute"`.
+    pub inline_code: InlineCodeSyntax,
+    pub theme: Theme,
+    pub document: DocumentOptions,
+    pub figure: FigureOptions,
+    pub svg: SvgOptions,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        return RenderOptions {
+            format: OutputFormat::default(),
+            line_numbers: false,
+            class_scheme: ClassScheme::default(),
+            class_prefix: "code-".into(),
+            inline_styles: false,
+            plain_fallback: false,
+            default_language: None,
+            inline_code: InlineCodeSyntax::default(),
+            theme: Theme::default(),
+            document: DocumentOptions::default(),
+            figure: FigureOptions::default(),
+            svg: SvgOptions::default(),
+        };
+    }
+}
+
+impl RenderOptions {
+    /// Returns the configured theme, or [`Theme::builtin`] if it has no styles, for outputs which
+    /// can't do without colors.
+    pub fn theme_or_builtin(&self) -> Theme {
+        if self.theme.styles.is_empty() {
+            return Theme::builtin();
+        }
+        return self.theme.clone();
+    }
+}
@@ -0,0 +1,219 @@
+use super::treesitter_util::HIGHLIGHT_NAMES;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A style for a single capture name. In `mordant.toml` this can either be a table,
+/// or just a color as a shorthand, e.g. `comment = "#928374"`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(from = "StyleSrc")]
+pub struct Style {
+    pub color: Option<String>,
+    pub background: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StyleSrc {
+    Color(String),
+    Full {
+        color: Option<String>,
+        background: Option<String>,
+        #[serde(default)]
+        bold: bool,
+        #[serde(default)]
+        italic: bool,
+        #[serde(default)]
+        underline: bool,
+        #[serde(default)]
+        strikethrough: bool,
+    },
+}
+
+impl From<StyleSrc> for Style {
+    fn from(src: StyleSrc) -> Self {
+        match src {
+            StyleSrc::Color(color) => {
+                return Style {
+                    color: Some(color),
+                    ..Default::default()
+                };
+            }
+            StyleSrc::Full {
+                color,
+                background,
+                bold,
+                italic,
+                underline,
+                strikethrough,
+            } => {
+                return Style {
+                    color,
+                    background,
+                    bold,
+                    italic,
+                    underline,
+                    strikethrough,
+                };
+            }
+        }
+    }
+}
+
+impl Style {
+    /// Returns the css declarations for this [`Style`] as `(property, value)` pairs.
+    /// Colors are passed through `color`, e.g. to look them up in a palette.
+    pub fn declarations(&self, color: impl Fn(&str) -> String) -> Vec<(&'static str, String)> {
+        let mut declarations = Vec::new();
+        if let Some(fg) = &self.color {
+            declarations.push(("color", color(fg)));
+        }
+        if let Some(bg) = &self.background {
+            declarations.push(("background-color", color(bg)));
+        }
+        if self.bold {
+            declarations.push(("font-weight", "bold".into()));
+        }
+        if self.italic {
+            declarations.push(("font-style", "italic".into()));
+        }
+        match (self.underline, self.strikethrough) {
+            (true, true) => declarations.push(("text-decoration", "underline line-through".into())),
+            (true, false) => declarations.push(("text-decoration", "underline".into())),
+            (false, true) => declarations.push(("text-decoration", "line-through".into())),
+            (false, false) => {}
+        }
+        return declarations;
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+impl ColorScheme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Light => return "light",
+            Self::Dark => return "dark",
+        }
+    }
+}
+
+/// Palette and style overrides for a single [`ColorScheme`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ThemeVariant {
+    #[serde(default)]
+    pub palette: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub styles: BTreeMap<String, Style>,
+}
+
+/// Parses `#rrggbb` or `#rgb` colors. Other css colors are not supported.
+pub fn parse_hex(color: &str) -> Option<(u8, u8, u8)> {
+    let hex = color.strip_prefix('#')?;
+    let channel = |i: usize, len: usize| u8::from_str_radix(hex.get(i..i + len)?, 16).ok();
+    match hex.len() {
+        6 => return Some((channel(0, 2)?, channel(2, 2)?, channel(4, 2)?)),
+        3 => {
+            return Some((
+                channel(0, 1)? * 17,
+                channel(1, 1)? * 17,
+                channel(2, 1)? * 17,
+            ));
+        }
+        _ => return None,
+    }
+}
+
+/// Styles keyed by the names in [`super::treesitter_util::HIGHLIGHT_NAMES`].
+/// Configured through the `[theme]` table of `mordant.toml`, or a standalone theme file for
+/// `mordant css`.
+///
+/// Colors may either be css colors, or names from the `palette`. `light` and `dark` may override
+/// both palette entries and styles for the respective `prefers-color-scheme`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Theme {
+    #[serde(default)]
+    pub palette: BTreeMap<String, String>,
+    pub light: Option<ThemeVariant>,
+    pub dark: Option<ThemeVariant>,
+    #[serde(flatten)]
+    pub styles: BTreeMap<String, Style>,
+}
+
+impl Theme {
+    /// Returns the theme from `example-theme.toml`, for outputs that can't do without one.
+    pub fn builtin() -> Theme {
+        return toml::from_str(include_str!("../../example-theme.toml")).unwrap();
+    }
+
+    /// Returns the style for the provided highlight name. If there is no entry for the name,
+    /// fall back to its dotted parent, e.g. `keyword.return` -> `keyword`.
+    pub fn style_for(&self, name: &str) -> Option<&Style> {
+        let mut name = name;
+        loop {
+            if let Some(style) = self.styles.get(name) {
+                return Some(style);
+            }
+            match name.rsplit_once('.') {
+                Some((parent, _)) => name = parent,
+                None => return None,
+            }
+        }
+    }
+
+    /// Returns the entries of [`HIGHLIGHT_NAMES`] without a style, or a style for any of their
+    /// dotted parents.
+    pub fn missing_styles(&self) -> Vec<&'static str> {
+        return HIGHLIGHT_NAMES
+            .into_iter()
+            .filter(|name| return self.style_for(name).is_none())
+            .collect();
+    }
+
+    /// Looks `color` up in the palette, returning it unchanged if it is not a palette entry.
+    pub fn resolve_color(&self, color: &str) -> String {
+        return self.palette.get(color).cloned().unwrap_or(color.into());
+    }
+
+    /// Returns the inline css for the provided highlight name, with palette colors resolved,
+    /// e.g. `color:#fb4934;font-weight:bold`.
+    pub fn inline_css(&self, name: &str) -> Option<String> {
+        let style = self.style_for(name)?;
+        let declarations: Vec<String> = style
+            .declarations(|c| self.resolve_color(c))
+            .iter()
+            .map(|(property, value)| format!("{property}:{value}"))
+            .collect();
+        return Some(declarations.join(";"));
+    }
+
+    /// Returns the variant for `scheme`, if one is configured.
+    pub fn variant(&self, scheme: ColorScheme) -> Option<&ThemeVariant> {
+        match scheme {
+            ColorScheme::Light => return self.light.as_ref(),
+            ColorScheme::Dark => return self.dark.as_ref(),
+        }
+    }
+
+    /// Returns this theme, with the overrides for `scheme` applied.
+    pub fn with_scheme(&self, scheme: ColorScheme) -> Theme {
+        let mut theme = Theme {
+            palette: self.palette.clone(),
+            light: None,
+            dark: None,
+            styles: self.styles.clone(),
+        };
+        if let Some(variant) = self.variant(scheme) {
+            theme.palette.extend(variant.palette.clone());
+            theme.styles.extend(variant.styles.clone());
+        }
+        return theme;
+    }
+}
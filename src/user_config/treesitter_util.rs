@@ -185,6 +185,20 @@ pub fn get_builtin_language(name: &str) -> MordantConfigResult<Language> {
         }
     }
 }
+/// Returns the aliases every configuration for `name` gets, in addition to those configured in
+/// its `aliases`, e.g. `py` for `python`. Only other names for the same language are aliases,
+/// not dialects like `luau`, which the grammar may not parse.
+pub fn get_builtin_aliases(name: &str) -> &'static [&'static str] {
+    match name {
+        "javascript" => return &["js", "jsx", "mjs", "cjs", "node"],
+        "python" => return &["py", "py3", "python3"],
+        "typescript" => return &["ts", "mts", "cts"],
+        "html" => return &["htm", "xhtml"],
+        "rust" => return &["rs"],
+        "json" => return &["jsonc"],
+        _ => return &[],
+    }
+}
 pub fn get_builtin_highlights(name: &str) -> MordantConfigResult<String> {
     match name {
         #[cfg(feature = "javascript")]